@@ -0,0 +1,60 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use exprimo::Program;
+use serde_json::json;
+use std::hint::black_box;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const RULES: &[(&str, &str)] = &[
+    ("arithmetic", "(a + b) * 2 - a / 4 > 10 && b % 3 == 1"),
+    (
+        "properties",
+        "user.address.city == 'Paris' && user.age >= 18 || user.tags.join(',').contains('vip')",
+    ),
+    (
+        "conditional",
+        "user.age > 65 ? 'senior' : user.age > 18 ? 'adult' : 'minor'",
+    ),
+    ("methods", "user.name.trim().toLowerCase().startsWith('ad')"),
+];
+
+fn bench_rules(c: &mut Criterion) {
+    let evaluator = common::evaluator(&[]);
+    let variables = json!({
+        "a": 12,
+        "b": 7,
+        "user": {
+            "name": " Ada ",
+            "age": 36,
+            "address": { "city": "Paris" },
+            "tags": ["staff", "vip"],
+        },
+    });
+
+    let mut group = c.benchmark_group("rules");
+    for (name, source) in RULES {
+        let expression = evaluator.compile(source).unwrap();
+        let program = Program::compile(&expression);
+
+        group.bench_with_input(BenchmarkId::new("evaluate", name), source, |b, source| {
+            b.iter(|| evaluator.evaluate_with(black_box(source), &variables))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("evaluate_expression", name),
+            &expression,
+            |b, expression| {
+                b.iter(|| evaluator.evaluate_expression(black_box(expression), &variables))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("evaluate_program", name),
+            &program,
+            |b, program| b.iter(|| evaluator.evaluate_program(black_box(program), &variables)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_rules);
+criterion_main!(benches);
@@ -1,3 +1,5 @@
+#![allow(clippy::unwrap_or_default, clippy::useless_format)]
+
 use exprimo::ContextEntry;
 use serde_json::Value;
 use std::collections::HashMap;
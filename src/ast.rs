@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Byte range of a node in the source expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExprKind {
+    Literal(Value),
+    Identifier(String),
+    /// Property read, e.g. `a.b`, or `a?.b` when `optional`.
+    Member {
+        object: Box<Expr>,
+        property: String,
+        optional: bool,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Conditional {
+        test: Box<Expr>,
+        consequent: Box<Expr>,
+        alternate: Box<Expr>,
+    },
+    /// Call of a function from the context, e.g. `mul(a, b)`.
+    Call {
+        function: String,
+        arguments: Vec<Expr>,
+    },
+    /// Call of a built-in method on a value, e.g. `a.toUpperCase()`.
+    MethodCall {
+        receiver: Box<Expr>,
+        method: String,
+        arguments: Vec<Expr>,
+    },
+    /// A construct outside the supported subset, described for the error raised when it is
+    /// evaluated.
+    Unsupported(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnaryOp {
+    Not,
+    Minus,
+    Plus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Eq,
+    StrictEq,
+    NotEq,
+    StrictNotEq,
+    Gt,
+    Lt,
+    GtEq,
+    LtEq,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+
+    /// Calls `f` on this node and then on each of its descendants, in source order.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        f(self);
+        match &self.kind {
+            ExprKind::Literal(_) | ExprKind::Identifier(_) | ExprKind::Unsupported(_) => {}
+            ExprKind::Member { object, .. } => object.walk(f),
+            ExprKind::Unary { operand, .. } => operand.walk(f),
+            ExprKind::Binary { left, right, .. } => {
+                left.walk(f);
+                right.walk(f);
+            }
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => {
+                test.walk(f);
+                consequent.walk(f);
+                alternate.walk(f);
+            }
+            ExprKind::Call { arguments, .. } => arguments.iter().for_each(|arg| arg.walk(f)),
+            ExprKind::MethodCall {
+                receiver,
+                arguments,
+                ..
+            } => {
+                receiver.walk(f);
+                arguments.iter().for_each(|arg| arg.walk(f));
+            }
+        }
+    }
+}
+
+impl UnaryOp {
+    /// The operator as written in source.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UnaryOp::Not => "!",
+            UnaryOp::Minus => "-",
+            UnaryOp::Plus => "+",
+        }
+    }
+}
+
+impl BinaryOp {
+    /// The operator as written in source.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::Eq => "==",
+            BinaryOp::StrictEq => "===",
+            BinaryOp::NotEq => "!=",
+            BinaryOp::StrictNotEq => "!==",
+            BinaryOp::Gt => ">",
+            BinaryOp::Lt => "<",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::LtEq => "<=",
+        }
+    }
+}
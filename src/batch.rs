@@ -0,0 +1,277 @@
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::cancel::CancellationToken;
+use crate::error::EvaluationError;
+use crate::expression::Expression;
+use crate::scope::Scope;
+use crate::vm::Program;
+use crate::Evaluator;
+
+/// Rows each word of a [`Selection`] holds, and the unit of work when evaluating in parallel.
+const WORD: usize = 64;
+
+/// Which rows an expression selected, from [`Evaluator::select`].
+#[derive(Debug, Default)]
+pub struct Selection {
+    words: Vec<u64>,
+    len: usize,
+    errors: Vec<(usize, EvaluationError)>,
+}
+
+impl Selection {
+    /// Number of rows evaluated, selected or not.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of rows selected.
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        row < self.len && self.words[row / WORD] & (1 << (row % WORD)) != 0
+    }
+
+    /// Indices of the selected rows, in order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(|row| self.contains(*row))
+    }
+
+    /// The bitmap itself: row `i` is selected if bit `i % 64` of word `i / 64` is set.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Rows that failed to evaluate, which aren't selected, with their errors.
+    pub fn errors(&self) -> &[(usize, EvaluationError)] {
+        &self.errors
+    }
+
+    fn push(&mut self, word: u64, rows: usize, errors: Vec<(usize, EvaluationError)>) {
+        self.words.push(word);
+        self.len += rows;
+        self.errors.extend(errors);
+    }
+}
+
+/// Records as columns of values, one per variable, for evaluating an expression over each
+/// row without building an object per row.
+///
+/// ```
+/// # use exprimo::Columns;
+/// # use serde_json::json;
+/// let amounts = [json!(120), json!(80)];
+/// let countries = [json!("NZ"), json!("AU")];
+/// let columns = Columns::new()
+///     .column("amount", &amounts)
+///     .column("country", &countries);
+/// assert_eq!(columns.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Columns<'c> {
+    columns: HashMap<&'c str, &'c [Value]>,
+}
+
+impl<'c> Columns<'c> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the variable `name`, whose value in row `i` is `values[i]`. A column shorter than
+    /// the others reads as null past its end.
+    pub fn column(mut self, name: &'c str, values: &'c [Value]) -> Self {
+        self.columns.insert(name, values);
+        self
+    }
+
+    /// Number of rows, the length of the longest column.
+    pub fn len(&self) -> usize {
+        self.columns
+            .values()
+            .map(|values| values.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// One scope per row, to pass to [`Evaluator::evaluate_batch`] or [`Evaluator::select`].
+    pub fn rows(&self) -> Vec<ColumnRow<'_>> {
+        (0..self.len())
+            .map(|row| ColumnRow { columns: self, row })
+            .collect()
+    }
+}
+
+/// One row of [`Columns`], reading each variable from its column.
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnRow<'c> {
+    columns: &'c Columns<'c>,
+    row: usize,
+}
+
+impl Scope for ColumnRow<'_> {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        let values = self.columns.columns.get(name)?;
+        Some(match values.get(self.row) {
+            Some(value) => Cow::Borrowed(value),
+            None => Cow::Owned(Value::Null),
+        })
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.columns.columns.keys().copied().collect()
+    }
+}
+
+impl Evaluator {
+    /// Evaluates `expression` against each of `rows`, returning the results in row order.
+    ///
+    /// The expression is lowered to a [`Program`] once for the whole batch, so each row
+    /// only pays for running it.
+    pub fn evaluate_batch<R: Scope>(
+        &self,
+        expression: &Expression,
+        rows: &[R],
+    ) -> Vec<Result<Value, EvaluationError>> {
+        self.evaluate_batch_cancellable(expression, rows, &CancellationToken::never())
+    }
+
+    /// Like [`evaluate_batch`](Evaluator::evaluate_batch), but once `token` is cancelled or
+    /// its deadline passes, each row left fails with
+    /// [`ErrorKind::Cancelled`](crate::ErrorKind::Cancelled) or
+    /// [`ErrorKind::Timeout`](crate::ErrorKind::Timeout) as soon as it starts.
+    pub fn evaluate_batch_cancellable<R: Scope>(
+        &self,
+        expression: &Expression,
+        rows: &[R],
+        token: &CancellationToken,
+    ) -> Vec<Result<Value, EvaluationError>> {
+        let program = Program::compile(expression);
+        rows.iter()
+            .map(|row| self.evaluate_program_cancellable(&program, row, token))
+            .collect()
+    }
+
+    /// Evaluates `expression` as a predicate against each of `rows`, selecting the rows
+    /// for which it is truthy.
+    pub fn select<R: Scope>(&self, expression: &Expression, rows: &[R]) -> Selection {
+        self.select_cancellable(expression, rows, &CancellationToken::never())
+    }
+
+    /// Like [`select`](Evaluator::select), but once `token` is cancelled or its deadline
+    /// passes, each row left fails as with
+    /// [`evaluate_batch_cancellable`](Evaluator::evaluate_batch_cancellable) and isn't
+    /// selected.
+    pub fn select_cancellable<R: Scope>(
+        &self,
+        expression: &Expression,
+        rows: &[R],
+        token: &CancellationToken,
+    ) -> Selection {
+        let program = Program::compile(expression);
+        let mut selection = Selection::default();
+        for (index, chunk) in rows.chunks(WORD).enumerate() {
+            let (word, errors) = self.select_word(&program, chunk, index * WORD, token);
+            selection.push(word, chunk.len(), errors);
+        }
+        selection
+    }
+
+    /// Like [`evaluate_batch`](Evaluator::evaluate_batch), spreading the rows over rayon's
+    /// thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_evaluate_batch<R: Scope + Sync>(
+        &self,
+        expression: &Expression,
+        rows: &[R],
+    ) -> Vec<Result<Value, EvaluationError>> {
+        self.par_evaluate_batch_cancellable(expression, rows, &CancellationToken::never())
+    }
+
+    /// Like [`evaluate_batch_cancellable`](Evaluator::evaluate_batch_cancellable), spreading
+    /// the rows over rayon's thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_evaluate_batch_cancellable<R: Scope + Sync>(
+        &self,
+        expression: &Expression,
+        rows: &[R],
+        token: &CancellationToken,
+    ) -> Vec<Result<Value, EvaluationError>> {
+        use rayon::prelude::*;
+
+        let program = Program::compile(expression);
+        rows.par_iter()
+            .with_min_len(WORD)
+            .map(|row| self.evaluate_program_cancellable(&program, row, token))
+            .collect()
+    }
+
+    /// Like [`select`](Evaluator::select), spreading the rows over rayon's thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_select<R: Scope + Sync>(&self, expression: &Expression, rows: &[R]) -> Selection {
+        self.par_select_cancellable(expression, rows, &CancellationToken::never())
+    }
+
+    /// Like [`select_cancellable`](Evaluator::select_cancellable), spreading the rows over
+    /// rayon's thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_select_cancellable<R: Scope + Sync>(
+        &self,
+        expression: &Expression,
+        rows: &[R],
+        token: &CancellationToken,
+    ) -> Selection {
+        use rayon::prelude::*;
+
+        let program = Program::compile(expression);
+        let words: Vec<_> = rows
+            .par_chunks(WORD)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let word = self.select_word(&program, chunk, index * WORD, token);
+                (word, chunk.len())
+            })
+            .collect();
+        let mut selection = Selection::default();
+        for ((word, errors), rows) in words {
+            selection.push(word, rows, errors);
+        }
+        selection
+    }
+
+    /// Evaluates up to one word of rows, the first of which is row `offset`.
+    fn select_word<R: Scope>(
+        &self,
+        program: &Program<'_>,
+        rows: &[R],
+        offset: usize,
+        token: &CancellationToken,
+    ) -> (u64, Vec<(usize, EvaluationError)>) {
+        let mut word = 0;
+        let mut errors = Vec::new();
+        for (bit, row) in rows.iter().enumerate() {
+            match self.evaluate_program_cancellable(program, row, token) {
+                Ok(value) => {
+                    if self.to_boolean(&value).unwrap_or(false) {
+                        word |= 1 << bit;
+                    }
+                }
+                Err(error) => errors.push((offset + bit, error)),
+            }
+        }
+        (word, errors)
+    }
+}
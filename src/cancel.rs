@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::ErrorKind;
+
+/// Stops an evaluation from another thread, or once a deadline passes.
+///
+/// The evaluator checks the token before evaluating each node and fails with
+/// [`ErrorKind::Cancelled`] or [`ErrorKind::Timeout`]. Functions registered with
+/// [`Evaluator::register_cancellable`](crate::Evaluator::register_cancellable) receive the
+/// token too, so long-running host work can give up early.
+///
+/// Clones share the cancellation flag: cancelling any of them cancels them all.
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    // `None` only for the internal token that can never be cancelled
+    cancelled: Option<Arc<AtomicBool>>,
+    deadline: Option<Instant>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken {
+            cancelled: Some(Arc::new(AtomicBool::new(false))),
+            deadline: None,
+        }
+    }
+
+    /// A token that is never cancelled and has no deadline, without allocating.
+    pub(crate) fn never() -> Self {
+        CancellationToken {
+            cancelled: None,
+            deadline: None,
+        }
+    }
+
+    /// A clone that also expires at `deadline`, or at its current deadline if that is earlier.
+    pub fn with_deadline(&self, deadline: Instant) -> Self {
+        CancellationToken {
+            cancelled: self.cancelled.clone(),
+            deadline: Some(self.deadline.map_or(deadline, |d| d.min(deadline))),
+        }
+    }
+
+    /// A clone that also expires `timeout` from now.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.with_deadline(deadline),
+            None => self.clone(),
+        }
+    }
+
+    pub fn cancel(&self) {
+        if let Some(cancelled) = &self.cancelled {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether [`cancel`](CancellationToken::cancel) was called on this token or a clone.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+            .as_ref()
+            .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Whether the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Whether work should stop, because the token was cancelled or has expired.
+    pub fn should_stop(&self) -> bool {
+        self.is_cancelled() || self.is_expired()
+    }
+
+    /// The error to stop with, if work should stop.
+    pub(crate) fn check(&self) -> Result<(), ErrorKind> {
+        if self.is_cancelled() {
+            Err(ErrorKind::Cancelled)
+        } else if self.is_expired() {
+            Err(ErrorKind::Timeout)
+        } else {
+            Ok(())
+        }
+    }
+}
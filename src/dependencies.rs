@@ -0,0 +1,100 @@
+use std::collections::BTreeSet;
+
+use crate::ast::{Expr, ExprKind};
+use crate::error::EvaluationError;
+use crate::expression::Expression;
+use crate::{member_chain, Evaluator};
+
+/// The variables and functions an expression references, found without evaluating it.
+///
+/// Every branch counts, so a conditional reports what either side reads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    /// Variables read, each as the path of properties read from it, e.g. `event.user.id`.
+    /// A variable read whole, or only passed on, appears as its bare name.
+    pub variables: BTreeSet<String>,
+    /// Names of the functions called.
+    pub functions: BTreeSet<String>,
+}
+
+impl Dependencies {
+    /// Names of the top-level variables read, e.g. `event` for `event.user.id`.
+    pub fn roots(&self) -> BTreeSet<&str> {
+        self.variables
+            .iter()
+            .map(|path| path.split('.').next().unwrap_or(path))
+            .collect()
+    }
+
+    fn collect(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Identifier(name) => {
+                self.variables.insert(name.clone());
+            }
+            ExprKind::Member { .. } => {
+                let chain = member_chain(expr);
+                match &chain.root.kind {
+                    ExprKind::Identifier(name) => {
+                        let mut path = name.clone();
+                        for property in &chain.properties {
+                            path.push('.');
+                            path.push_str(property);
+                        }
+                        self.variables.insert(path);
+                    }
+                    // Properties of a computed value, e.g. `lookup(id).name`, read nothing
+                    // from the variables themselves
+                    _ => self.collect(chain.root),
+                }
+            }
+            ExprKind::Call {
+                function,
+                arguments,
+            } => {
+                self.functions.insert(function.clone());
+                arguments.iter().for_each(|arg| self.collect(arg));
+            }
+            ExprKind::MethodCall {
+                receiver,
+                arguments,
+                ..
+            } => {
+                self.collect(receiver);
+                arguments.iter().for_each(|arg| self.collect(arg));
+            }
+            ExprKind::Unary { operand, .. } => self.collect(operand),
+            ExprKind::Binary { left, right, .. } => {
+                self.collect(left);
+                self.collect(right);
+            }
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => {
+                self.collect(test);
+                self.collect(consequent);
+                self.collect(alternate);
+            }
+            ExprKind::Literal(_) | ExprKind::Unsupported(_) => {}
+        }
+    }
+}
+
+impl Expression {
+    /// The variables and functions this expression references.
+    pub fn dependencies(&self) -> Dependencies {
+        let mut dependencies = Dependencies::default();
+        dependencies.collect(self.root());
+        dependencies
+    }
+}
+
+impl Evaluator {
+    /// Compiles `expression` and lists the variables and functions it references, e.g. to
+    /// fetch only the parts of a payload a rule reads.
+    pub fn dependencies(&self, expression: &str) -> Result<Dependencies, EvaluationError> {
+        self.compile(expression)
+            .map(|expression| expression.dependencies())
+    }
+}
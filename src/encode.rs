@@ -0,0 +1,156 @@
+use serde::de::{self, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use thiserror::Error;
+
+use crate::ast::{Expr, ExprKind};
+use crate::expression::Expression;
+
+/// Why bytes couldn't be decoded into an [`Expression`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// The expression was encoded in a format version this build can't read, e.g. by a
+    /// newer release during a rolling deploy.
+    #[error("Unsupported compiled expression format version {found}, expected {expected}")]
+    Version { found: u32, expected: u32 },
+    /// The bytes aren't a compiled expression.
+    #[error("Invalid compiled expression: {message}")]
+    Invalid { message: String },
+}
+
+impl Expression {
+    /// Version of the serialised form, bumped whenever a change to the tree would make
+    /// earlier or later releases read it differently.
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// Encodes the expression in the compact binary form, CBOR.
+    #[cfg(feature = "binary")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes).expect("writing to a Vec can't fail");
+        bytes
+    }
+
+    /// Decodes an expression encoded by [`to_bytes`](Expression::to_bytes), without parsing
+    /// its source again.
+    #[cfg(feature = "binary")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        // Read the version alone first, so a mismatch isn't reported as a malformed tree
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+
+        let invalid = |e: ciborium::de::Error<std::io::Error>| DecodeError::Invalid {
+            message: e.to_string(),
+        };
+        let header: Header = ciborium::from_reader(bytes).map_err(invalid)?;
+        check_version(header.version)?;
+        ciborium::from_reader(bytes).map_err(invalid)
+    }
+}
+
+fn check_version(found: u32) -> Result<(), DecodeError> {
+    if found == Expression::FORMAT_VERSION {
+        Ok(())
+    } else {
+        Err(DecodeError::Version {
+            found,
+            expected: Expression::FORMAT_VERSION,
+        })
+    }
+}
+
+/// Serialises as `{ "version", "source", "root" }`, see [`Expression`].
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Expression", 3)?;
+        state.serialize_field("version", &Expression::FORMAT_VERSION)?;
+        state.serialize_field("source", self.source())?;
+        state.serialize_field("root", self.root())?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct(
+            "Expression",
+            &["version", "source", "root"],
+            ExpressionVisitor,
+        )
+    }
+}
+
+struct ExpressionVisitor;
+
+impl<'de> Visitor<'de> for ExpressionVisitor {
+    type Value = Expression;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a compiled expression")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Expression, A::Error> {
+        let version = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        check_version(version).map_err(de::Error::custom)?;
+        let source: String = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let root: Expr = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        decoded(source, root)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Expression, A::Error> {
+        let mut version = None;
+        let mut source = None;
+        let mut root = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "version" => {
+                    let found = map.next_value()?;
+                    check_version(found).map_err(de::Error::custom)?;
+                    version = Some(found);
+                }
+                "source" => source = Some(map.next_value::<String>()?),
+                // The version is written first, so the tree is only read once it is checked
+                "root" => root = Some(map.next_value::<Expr>()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        version.ok_or_else(|| de::Error::missing_field("version"))?;
+        let source = source.ok_or_else(|| de::Error::missing_field("source"))?;
+        let root = root.ok_or_else(|| de::Error::missing_field("root"))?;
+        decoded(source, root)
+    }
+}
+
+/// Rebuilds an expression, checking that its spans lie within the source since errors
+/// quote the source they point at, and that its literals are ones the parser produces, so
+/// it prints back to source that parses.
+fn decoded<E: de::Error>(source: String, root: Expr) -> Result<Expression, E> {
+    let (mut spans, mut literals) = (true, true);
+    root.walk(&mut |node| {
+        spans &= source.get(node.span.start..node.span.end).is_some();
+        literals &= !matches!(
+            node.kind,
+            ExprKind::Literal(Value::Array(_) | Value::Object(_))
+        );
+    });
+    if !spans {
+        return Err(E::custom("node span outside the expression source"));
+    }
+    if !literals {
+        return Err(E::custom("array or object literal in the tree"));
+    }
+    Ok(Expression::new(source, root))
+}
@@ -0,0 +1,296 @@
+use std::fmt;
+use thiserror::Error;
+
+use crate::ast::Span;
+use crate::function::FunctionError;
+use crate::limits::Limit;
+use crate::policy::Capability;
+use crate::types::Type;
+
+/// An error raised while compiling or evaluating an expression, located in its source.
+///
+/// `{}` formats the message with its line and column. The alternate form `{:#}` renders
+/// the expression with the offending part underlined:
+///
+/// ```text
+/// error: Division by zero
+///  --> 1:5
+///   |
+/// 1 | a + b / 0
+///   |     ^^^^^
+/// ```
+#[derive(Debug)]
+pub struct EvaluationError {
+    kind: ErrorKind,
+    span: Span,
+    expression: String,
+    suggestion: Option<String>,
+}
+
+/// What went wrong, for callers that need to react to specific failures.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The expression could not be parsed.
+    #[error("{message}")]
+    Parse { message: String },
+    /// The expression uses syntax the evaluator does not support.
+    #[error("Unsupported {construct}")]
+    Unsupported { construct: String },
+    /// An identifier is not defined by the variables or the context.
+    #[error("Identifier '{name}' not found in context.")]
+    UndefinedVariable { name: String },
+    /// A property read in strict property mode doesn't exist. `path` is the expression the
+    /// property was read from, e.g. `event.user` for `event.user.usr`.
+    #[error("`{path}` has no property `{property}`")]
+    MissingProperty { path: String, property: String },
+    /// A called function is not registered.
+    #[error("Function '{name}' not found in context")]
+    UndefinedFunction { name: String },
+    /// An operator was applied to values it cannot handle. `left` and `right` are the
+    /// operand types; `right` is `None` for unary operators.
+    #[error("Cannot apply '{op}' to {}", operands(.left, .right))]
+    TypeError {
+        op: &'static str,
+        left: &'static str,
+        right: Option<&'static str>,
+    },
+    #[error("Division by zero")]
+    DivisionByZero,
+    /// The receiver type has no method with this name.
+    #[error("Unknown {receiver_type} method: {method}")]
+    UnknownMethod {
+        receiver_type: &'static str,
+        method: String,
+    },
+    /// A built-in method rejected its arguments.
+    #[error("Invalid arguments to '{method}': {message}")]
+    InvalidArguments { method: String, message: String },
+    /// A function or built-in method was called with the wrong number of arguments.
+    #[error("'{callee}' expected {} argument(s), got {found}", arity(*.min, *.max))]
+    ArityMismatch {
+        callee: String,
+        min: usize,
+        max: usize,
+        found: usize,
+    },
+    /// A function declared in a [`TypeContext`](crate::TypeContext) is passed an argument
+    /// of the wrong type. `position` counts from 1.
+    #[error("'{callee}' expects {expected} as argument {position}, got {found}")]
+    ArgumentType {
+        callee: String,
+        position: usize,
+        expected: Box<Type>,
+        found: Box<Type>,
+    },
+    /// A method taking a regular expression was given a pattern computed at evaluation
+    /// time, which the [`RegexPolicy`](crate::RegexPolicy) forbids.
+    #[error("'{method}' requires a string literal pattern")]
+    DynamicPattern { method: String },
+    /// The expression uses a function, method or global the
+    /// [`AccessPolicy`](crate::AccessPolicy) forbids.
+    #[error("Use of {capability} '{name}' is not allowed")]
+    Forbidden {
+        capability: Capability,
+        name: String,
+    },
+    /// A host function returned an error.
+    #[error("Error calling function '{name}': {source}")]
+    HostFunction { name: String, source: FunctionError },
+    /// Arithmetic produced a number JSON can't hold, such as `0 % 0` or an overflow.
+    #[error("Result of '{op}' is not a finite number")]
+    NonFiniteNumber { op: &'static str },
+    /// One of the [`EvaluationLimits`](crate::EvaluationLimits) was exceeded.
+    #[error("{limit} limit of {max} exceeded")]
+    LimitExceeded { limit: Limit, max: usize },
+    /// The evaluation ran past its deadline, from [`EvaluationLimits::timeout`] or the
+    /// [`CancellationToken`](crate::CancellationToken) it was given.
+    ///
+    /// [`EvaluationLimits::timeout`]: crate::EvaluationLimits::timeout
+    #[error("Evaluation timed out")]
+    Timeout,
+    /// The [`CancellationToken`](crate::CancellationToken) the evaluation was given was
+    /// cancelled.
+    #[error("Evaluation was cancelled")]
+    Cancelled,
+}
+
+fn operands(left: &str, right: &Option<&str>) -> String {
+    match right {
+        Some(right) => format!("{} and {}", left, right),
+        None => left.to_string(),
+    }
+}
+
+fn arity(min: usize, max: usize) -> String {
+    if min == max {
+        min.to_string()
+    } else {
+        format!("{} to {}", min, max)
+    }
+}
+
+/// An error raised while walking the tree, before it is tied to its expression.
+#[derive(Debug)]
+pub(crate) struct NodeError {
+    pub(crate) kind: ErrorKind,
+    pub(crate) span: Option<Span>,
+    pub(crate) suggestion: Option<String>,
+}
+
+impl NodeError {
+    pub(crate) fn at(kind: ErrorKind, span: Span) -> Self {
+        NodeError {
+            kind,
+            span: Some(span),
+            suggestion: None,
+        }
+    }
+
+    pub(crate) fn with_suggestion(mut self, suggestion: Option<String>) -> Self {
+        self.suggestion = suggestion;
+        self
+    }
+
+    /// Points the error at `span` unless a more precise location is already known.
+    pub(crate) fn or_span(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+}
+
+impl From<ErrorKind> for NodeError {
+    fn from(kind: ErrorKind) -> Self {
+        NodeError {
+            kind,
+            span: None,
+            suggestion: None,
+        }
+    }
+}
+
+impl EvaluationError {
+    pub(crate) fn new(error: NodeError, expression: &str) -> Self {
+        EvaluationError {
+            kind: error.kind,
+            span: error.span.unwrap_or(Span::new(0, expression.len())),
+            expression: expression.to_string(),
+            suggestion: error.suggestion,
+        }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Consumes the error, returning its kind.
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// A likely intended name when the error is about an unknown one, e.g. `toUpperCase`
+    /// for a call to `toUppercase`.
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+
+    /// The expression the error was raised for.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Byte range of the offending part of the expression.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// 1-based line of the start of the span.
+    pub fn line(&self) -> usize {
+        line_column(&self.expression, self.span().start).0
+    }
+
+    /// 1-based column, in characters, of the start of the span.
+    pub fn column(&self) -> usize {
+        line_column(&self.expression, self.span().start).1
+    }
+
+    fn render(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        let (start_line, start_column) = line_column(&self.expression, span.start);
+        let (end_line, end_column) = line_column(&self.expression, span.end);
+        let width = end_line.to_string().len();
+
+        writeln!(f, "error: {}", self.message())?;
+        writeln!(f, "{:>width$}--> {}:{}", "", start_line, start_column)?;
+        write!(f, "{:>width$} |", "")?;
+
+        for (index, line) in self.expression.split('\n').enumerate() {
+            let number = index + 1;
+            if number < start_line || number > end_line {
+                continue;
+            }
+            let from = if number == start_line {
+                start_column
+            } else {
+                1
+            };
+            let to = if number == end_line {
+                end_column
+            } else {
+                line.chars().count() + 1
+            };
+            write!(f, "\n{:>width$} | {}", number, line.trim_end_matches('\r'))?;
+            write!(
+                f,
+                "\n{:>width$} | {}{}",
+                "",
+                " ".repeat(from - 1),
+                "^".repeat(to.saturating_sub(from).max(1))
+            )?;
+        }
+
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n{:>width$} = help: did you mean `{}`?", "", suggestion)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for EvaluationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.kind.source()
+    }
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.render(f);
+        }
+        write!(
+            f,
+            "{} (line {}, column {})",
+            self.message(),
+            self.line(),
+            self.column()
+        )?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "; did you mean `{}`?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// 1-based line and character column of the byte `offset` in `source`.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
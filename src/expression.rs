@@ -0,0 +1,75 @@
+use std::fmt;
+
+use crate::ast::Expr;
+use crate::print::{print, FormatOptions};
+
+/// A compiled expression.
+///
+/// Compiling once and evaluating many times skips re-parsing, and the compiled form is
+/// `Send + Sync` so it can be shared between threads alongside the [`Evaluator`](crate::Evaluator).
+///
+/// # Serialised form
+///
+/// Compiled expressions implement `Serialize` and `Deserialize`, so they can be compiled
+/// once and shipped to where they are evaluated without parsing them again. With the
+/// `binary` feature, [`to_bytes`](Expression::to_bytes) and
+/// [`from_bytes`](Expression::from_bytes) use CBOR. The form of `a > 1`, as JSON:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "source": "a > 1",
+///   "root": {
+///     "kind": { "binary": {
+///       "op": "gt",
+///       "left": { "kind": { "identifier": "a" }, "span": { "start": 0, "end": 1 } },
+///       "right": { "kind": { "literal": 1.0 }, "span": { "start": 4, "end": 5 } }
+///     } },
+///     "span": { "start": 0, "end": 5 }
+///   }
+/// }
+/// ```
+///
+/// `version` is [`FORMAT_VERSION`](Expression::FORMAT_VERSION); reading any other version
+/// fails rather than guessing. `root` is the [`Expr`] tree, with each node's `kind` named
+/// after its [`ExprKind`](crate::ast::ExprKind) variant and operators after their
+/// [`UnaryOp`](crate::ast::UnaryOp) or [`BinaryOp`](crate::ast::BinaryOp) variant, in
+/// snake case. Spans are byte offsets into `source`, used to locate errors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    source: String,
+    root: Expr,
+}
+
+impl Expression {
+    pub(crate) fn new(source: impl Into<String>, root: Expr) -> Self {
+        Expression {
+            source: source.into(),
+            root,
+        }
+    }
+
+    /// The source text the expression was compiled from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn root(&self) -> &Expr {
+        &self.root
+    }
+
+    /// Prints the expression as normalised source: canonical spacing, the fewest
+    /// parentheses that keep its meaning, and consistent quotes. The output compiles back
+    /// to an equivalent expression.
+    pub fn format(&self, options: &FormatOptions) -> String {
+        print(&self.root, &self.source, options)
+    }
+}
+
+impl fmt::Display for Expression {
+    /// Prints the expression on one line with the default [`FormatOptions`], which for an
+    /// [optimized](crate::Evaluator::optimize) expression is its simplified form.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(&FormatOptions::default()))
+    }
+}
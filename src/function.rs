@@ -0,0 +1,185 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::cancel::CancellationToken;
+
+/// Error returned by a host function. Anything convertible into a boxed error works,
+/// including `String`, `&str`, `anyhow::Error` and any `std::error::Error`.
+pub type FunctionError = Box<dyn std::error::Error + Send + Sync>;
+
+type BoxHostFunction =
+    Box<dyn Fn(Vec<Value>, &CancellationToken) -> Result<Value, FunctionError> + Send + Sync>;
+
+/// A fallible function callable from expressions.
+///
+/// Errors returned by the function are reported as evaluation errors pointing at the call site.
+pub struct HostFunction {
+    arity: Option<usize>,
+    func: BoxHostFunction,
+}
+
+impl HostFunction {
+    /// Wraps a function receiving the raw argument list.
+    pub fn new<F, E>(func: F) -> Self
+    where
+        F: Fn(Vec<Value>) -> Result<Value, E> + Send + Sync + 'static,
+        E: Into<FunctionError>,
+    {
+        HostFunction {
+            arity: None,
+            func: Box::new(move |args, _: &CancellationToken| func(args).map_err(Into::into)),
+        }
+    }
+
+    /// Wraps a function receiving the raw argument list and the evaluation's
+    /// [`CancellationToken`], for long-running work that should stop when the evaluation is
+    /// cancelled or times out.
+    pub fn cancellable<F, E>(func: F) -> Self
+    where
+        F: Fn(Vec<Value>, &CancellationToken) -> Result<Value, E> + Send + Sync + 'static,
+        E: Into<FunctionError>,
+    {
+        HostFunction {
+            arity: None,
+            func: Box::new(move |args, token: &CancellationToken| {
+                func(args, token).map_err(Into::into)
+            }),
+        }
+    }
+
+    /// Wraps a function with typed arguments and an infallible return value,
+    /// e.g. `|x: f64, lo: f64, hi: f64| x.max(lo).min(hi)`.
+    pub fn typed<Args, F>(func: F) -> Self
+    where
+        F: IntoHostFunction<Args>,
+    {
+        func.into_host_function()
+    }
+
+    /// Wraps a function with typed arguments returning a `Result`.
+    pub fn try_typed<Args, F>(func: F) -> Self
+    where
+        F: TryIntoHostFunction<Args>,
+    {
+        func.try_into_host_function()
+    }
+
+    /// The number of arguments the function expects, when known.
+    pub fn arity(&self) -> Option<usize> {
+        self.arity
+    }
+
+    pub fn call(&self, args: Vec<Value>) -> Result<Value, FunctionError> {
+        self.call_with_token(args, &CancellationToken::never())
+    }
+
+    /// Calls the function on behalf of an evaluation that stops when `token` says so.
+    pub fn call_with_token(
+        &self,
+        args: Vec<Value>,
+        token: &CancellationToken,
+    ) -> Result<Value, FunctionError> {
+        if let Some(arity) = self.arity {
+            if args.len() != arity {
+                return Err(format!("expected {} argument(s), got {}", arity, args.len()).into());
+            }
+        }
+        (self.func)(args, token)
+    }
+}
+
+/// Conversion of a closure with `Deserialize` arguments and a `Serialize` return value.
+pub trait IntoHostFunction<Args> {
+    fn into_host_function(self) -> HostFunction;
+}
+
+/// Conversion of a closure with `Deserialize` arguments returning `Result<R, E>`.
+pub trait TryIntoHostFunction<Args> {
+    fn try_into_host_function(self) -> HostFunction;
+}
+
+fn from_arg<T: DeserializeOwned>(index: usize, arg: Value) -> Result<T, FunctionError> {
+    serde_json::from_value(normalize_numbers(arg))
+        .map_err(|e| format!("argument {}: {}", index + 1, e).into())
+}
+
+fn to_result<R: Serialize>(value: R) -> Result<Value, FunctionError> {
+    serde_json::to_value(value).map_err(|e| format!("invalid return value: {}", e).into())
+}
+
+// Expression numbers are always floats, so whole numbers are handed over as integers
+// to let integer parameters deserialize.
+fn normalize_numbers(value: Value) -> Value {
+    match value {
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() < (1u64 << 53) as f64 => {
+                Value::Number((f as i64).into())
+            }
+            _ => Value::Number(n),
+        },
+        Value::Array(arr) => Value::Array(arr.into_iter().map(normalize_numbers).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, normalize_numbers(v)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+macro_rules! impl_host_function {
+    ($count:expr; $($arg:ident),*) => {
+        impl<F, R, $($arg,)*> IntoHostFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + Send + Sync + 'static,
+            R: Serialize,
+            $($arg: DeserializeOwned,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_host_function(self) -> HostFunction {
+                HostFunction {
+                    arity: Some($count),
+                    func: Box::new(move |args, _: &CancellationToken| {
+                        let mut args = args.into_iter().enumerate();
+                        $(
+                            let (index, value) = args.next().unwrap();
+                            let $arg: $arg = from_arg(index, value)?;
+                        )*
+                        to_result(self($($arg),*))
+                    }),
+                }
+            }
+        }
+
+        impl<F, R, E, $($arg,)*> TryIntoHostFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Result<R, E> + Send + Sync + 'static,
+            R: Serialize,
+            E: Into<FunctionError>,
+            $($arg: DeserializeOwned,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn try_into_host_function(self) -> HostFunction {
+                HostFunction {
+                    arity: Some($count),
+                    func: Box::new(move |args, _: &CancellationToken| {
+                        let mut args = args.into_iter().enumerate();
+                        $(
+                            let (index, value) = args.next().unwrap();
+                            let $arg: $arg = from_arg(index, value)?;
+                        )*
+                        to_result(self($($arg),*).map_err(Into::into)?)
+                    }),
+                }
+            }
+        }
+    };
+}
+
+impl_host_function!(0;);
+impl_host_function!(1; A1);
+impl_host_function!(2; A1, A2);
+impl_host_function!(3; A1, A2, A3);
+impl_host_function!(4; A1, A2, A3, A4);
+impl_host_function!(5; A1, A2, A3, A4, A5);
+impl_host_function!(6; A1, A2, A3, A4, A5, A6);
@@ -1,36 +1,60 @@
+pub mod ast;
+mod batch;
+mod cancel;
+mod dependencies;
+mod encode;
+mod error;
+mod expression;
+pub mod function;
+mod limits;
 pub mod method;
+mod optimize;
+mod parse;
+mod partial;
+mod pattern;
+mod policy;
+mod print;
+mod rules;
+mod scope;
+mod suggest;
+mod types;
+mod validate;
+mod vm;
+use ast::{BinaryOp, Expr, ExprKind, Span, UnaryOp};
+pub use batch::{ColumnRow, Columns, Selection};
+pub use cancel::CancellationToken;
+pub use dependencies::Dependencies;
+pub use encode::DecodeError;
+use error::NodeError;
+pub use error::{ErrorKind, EvaluationError};
+pub use expression::Expression;
+pub use function::{FunctionError, HostFunction, IntoHostFunction, TryIntoHostFunction};
+use limits::Budget;
+pub use limits::{EvaluationLimits, Limit};
 use method::{array::ArrayMethod, str::StrMethod};
-use rslint_parser::{
-    ast::{BinExpr, BinOp, CallExpr, CondExpr, DotExpr, Expr, Name, NameRef, UnaryExpr, UnaryOp},
-    parse_text, AstNode, SyntaxKind, SyntaxNode,
-};
-
-use anyhow::{bail, Result};
-use thiserror::Error;
+pub use partial::Partial;
+use pattern::RegexCache;
+pub use pattern::RegexPolicy;
+pub use policy::{AccessPolicy, Capability};
+pub use print::{FormatOptions, Quote};
+use rules::Shared;
+pub use rules::{MatchMode, RuleMatches, RuleSet};
+pub use scope::Scope;
+pub use types::{FunctionType, Type, TypeContext};
+pub use validate::Diagnostic;
+pub use vm::Program;
 
 use serde_json::Value;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 
-type BoxFunction = Box<dyn Fn(Vec<Value>) -> Value>;
-
-#[derive(Error, Debug)]
-#[error("Evaluation error")]
-pub struct EvaluationError {
-    #[from]
-    source: NodeError,
-}
-
-#[derive(Error, Debug)]
-#[error("Node error {message}, node: {node:?}")]
-pub struct NodeError {
-    message: String,
-    node: Option<SyntaxNode>,
-}
+type BoxFunction = Box<dyn Fn(Vec<Value>) -> Value + Send + Sync>;
 
 pub enum ContextEntry {
     Variable(Value),
     Function(BoxFunction),
+    HostFunction(HostFunction),
 }
 
 #[cfg(feature = "logging")]
@@ -38,44 +62,323 @@ use scribe_rust::Logger;
 #[cfg(feature = "logging")]
 use std::sync::Arc;
 
+/// Holds the functions and base variables shared by every evaluation.
+///
+/// An `Evaluator` is `Send + Sync`: wrap it in an `Arc` and evaluate compiled expressions
+/// against per-evaluation variables from as many threads as needed.
 pub struct Evaluator {
-    context: HashMap<String, ContextEntry>,
+    functions: HashMap<String, HostFunction>,
+    scope: Box<dyn Scope + Send + Sync>,
+    strict_syntax: bool,
+    strict_properties: bool,
+    limits: EvaluationLimits,
+    regexes: RegexCache,
+    policy: AccessPolicy,
     #[cfg(feature = "logging")]
     logger: Arc<Logger>,
 }
 
+/// Per-evaluation state.
+struct Frame<'a> {
+    source: &'a str,
+    variables: &'a dyn Scope,
+    base: &'a dyn Scope,
+    budget: Budget,
+    /// Values shared with the other rules of a [`RuleSet`] being evaluated.
+    shared: Option<Shared<'a>>,
+}
+
+impl<'a> Frame<'a> {
+    /// Takes a value computed during evaluation, accounting for it in the budget.
+    fn produced(&self, value: Value) -> Result<Cow<'a, Value>, NodeError> {
+        self.budget.account(&value)?;
+        Ok(Cow::Owned(value))
+    }
+
+    fn lookup(&self, name: &str) -> Option<Cow<'a, Value>> {
+        let (variables, base) = (self.variables, self.base);
+        variables.get(name).or_else(|| base.get(name))
+    }
+
+    fn lookup_path(&self, name: &str, path: &[&str]) -> Option<Cow<'a, Value>> {
+        let (variables, base) = (self.variables, self.base);
+        variables
+            .get_path(name, path)
+            .or_else(|| base.get_path(name, path))
+    }
+}
+
 impl Evaluator {
     pub fn new(
         context: HashMap<String, ContextEntry>,
         #[cfg(feature = "logging")] logger: Arc<Logger>,
+    ) -> Self {
+        let mut variables = HashMap::new();
+        let mut functions = HashMap::new();
+        for (name, entry) in context {
+            match entry {
+                ContextEntry::Variable(value) => {
+                    variables.insert(name, value);
+                }
+                ContextEntry::Function(f) => {
+                    functions.insert(
+                        name,
+                        HostFunction::new(move |args| Ok::<_, FunctionError>(f(args))),
+                    );
+                }
+                ContextEntry::HostFunction(f) => {
+                    functions.insert(name, f);
+                }
+            }
+        }
+
+        let mut evaluator = Self::with_scope(
+            variables,
+            #[cfg(feature = "logging")]
+            logger,
+        );
+        evaluator.functions = functions;
+        evaluator
+    }
+
+    /// Creates an evaluator whose base variables are resolved by `scope` instead of a
+    /// context map, e.g. a host-backed [`Scope`] that loads variables on demand.
+    pub fn with_scope(
+        scope: impl Scope + Send + Sync + 'static,
+        #[cfg(feature = "logging")] logger: Arc<Logger>,
     ) -> Self {
         Evaluator {
-            context,
+            functions: HashMap::new(),
+            scope: Box::new(scope),
+            strict_syntax: false,
+            strict_properties: false,
+            limits: EvaluationLimits::default(),
+            regexes: RegexCache::new(RegexPolicy::default()),
+            policy: AccessPolicy::default(),
             #[cfg(feature = "logging")]
             logger,
         }
     }
 
-    pub fn evaluate(&self, expression: &str) -> Result<Value> {
-        let ast = parse_text(expression, 0).syntax();
-        let untyped_expr_node = match ast.first_child() {
-            Some(node) => node,
-            None => {
-                return Err(NodeError {
-                    message: "Empty expression".to_string(),
-                    node: None,
+    /// Registers a fallible function receiving the raw argument list.
+    pub fn register_function<F, E>(&mut self, name: impl Into<String>, func: F) -> &mut Self
+    where
+        F: Fn(Vec<Value>) -> Result<Value, E> + Send + Sync + 'static,
+        E: Into<FunctionError>,
+    {
+        self.register(name, HostFunction::new(func))
+    }
+
+    /// Registers a fallible function receiving the raw argument list and the evaluation's
+    /// [`CancellationToken`], so it can stop early when the evaluation is cancelled or times
+    /// out.
+    pub fn register_cancellable<F, E>(&mut self, name: impl Into<String>, func: F) -> &mut Self
+    where
+        F: Fn(Vec<Value>, &CancellationToken) -> Result<Value, E> + Send + Sync + 'static,
+        E: Into<FunctionError>,
+    {
+        self.register(name, HostFunction::cancellable(func))
+    }
+
+    /// Registers a function with typed arguments, converted from the call arguments via serde.
+    pub fn register_fn<Args, F>(&mut self, name: impl Into<String>, func: F) -> &mut Self
+    where
+        F: IntoHostFunction<Args>,
+    {
+        self.register(name, HostFunction::typed(func))
+    }
+
+    /// Registers a function with typed arguments returning a `Result`.
+    pub fn try_register_fn<Args, F>(&mut self, name: impl Into<String>, func: F) -> &mut Self
+    where
+        F: TryIntoHostFunction<Args>,
+    {
+        self.register(name, HostFunction::try_typed(func))
+    }
+
+    /// Rejects expressions using constructs outside the supported subset when they are
+    /// compiled, instead of failing only if the construct is evaluated.
+    pub fn set_strict_syntax(&mut self, strict: bool) -> &mut Self {
+        self.strict_syntax = strict;
+        self
+    }
+
+    /// Makes reading a property that doesn't exist an error naming the missing property,
+    /// instead of yielding null. Reads written with `?.`, such as `user?.address`, still
+    /// yield null, and so does the rest of the chain after them.
+    pub fn set_strict_properties(&mut self, strict: bool) -> &mut Self {
+        self.strict_properties = strict;
+        self
+    }
+
+    /// Bounds the work each evaluation may do. [`EvaluationLimits::default`] applies until
+    /// this is called.
+    pub fn set_limits(&mut self, limits: EvaluationLimits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> &EvaluationLimits {
+        &self.limits
+    }
+
+    /// Sets how regular expression patterns are compiled and cached. [`RegexPolicy::default`]
+    /// applies until this is called. Patterns compiled so far are dropped.
+    pub fn set_regex_policy(&mut self, policy: RegexPolicy) -> &mut Self {
+        self.regexes = RegexCache::new(policy);
+        self
+    }
+
+    pub fn regex_policy(&self) -> &RegexPolicy {
+        self.regexes.policy()
+    }
+
+    /// Restricts the functions, methods and globals expressions may use. Everything is
+    /// allowed until this is called.
+    pub fn set_access_policy(&mut self, policy: AccessPolicy) -> &mut Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn access_policy(&self) -> &AccessPolicy {
+        &self.policy
+    }
+
+    fn register(&mut self, name: impl Into<String>, func: HostFunction) -> &mut Self {
+        self.functions.insert(name.into(), func);
+        self
+    }
+
+    /// Parses an expression once so it can be evaluated many times, possibly from
+    /// several threads.
+    pub fn compile(&self, expression: &str) -> Result<Expression, EvaluationError> {
+        self.compile_with(expression, parse::parse)
+    }
+
+    /// Compiles like [`compile`](Evaluator::compile), parsing with the full JavaScript
+    /// parser `rslint_parser` instead of exprimo's own, to compare the two.
+    #[cfg(feature = "rslint")]
+    pub fn compile_with_rslint(&self, expression: &str) -> Result<Expression, EvaluationError> {
+        self.compile_with(expression, parse::parse_rslint)
+    }
+
+    fn compile_with(
+        &self,
+        expression: &str,
+        parse: fn(&str) -> Result<Expr, NodeError>,
+    ) -> Result<Expression, EvaluationError> {
+        let root = limits::check_depth(expression, self.limits.max_depth)
+            .and_then(|_| parse(expression))
+            .map_err(|e| EvaluationError::new(e, expression))?;
+
+        if self.strict_syntax {
+            let mut unsupported = None;
+            root.walk(&mut |node| {
+                if let ExprKind::Unsupported(construct) = &node.kind {
+                    unsupported.get_or_insert(NodeError::at(
+                        ErrorKind::Unsupported {
+                            construct: construct.clone(),
+                        },
+                        node.span,
+                    ));
                 }
-                .into())
+            });
+            if let Some(error) = unsupported {
+                return Err(EvaluationError::new(error, expression));
             }
-        };
+        }
 
         #[cfg(feature = "logging")]
         self.logger.trace(&format!(
             "Expression AST:\n\n{:#?}\n-----------------",
-            untyped_expr_node
+            root
         ));
 
-        let result = self.evaluate_node(&untyped_expr_node)?;
+        Ok(Expression::new(expression, root))
+    }
+
+    pub fn evaluate(&self, expression: &str) -> Result<Value, EvaluationError> {
+        self.evaluate_with(expression, &Value::Null)
+    }
+
+    /// Evaluates an expression against borrowed per-call variables, e.g. a JSON object whose
+    /// keys become variables. Names not defined by `variables` fall through to the context.
+    pub fn evaluate_with<S>(
+        &self,
+        expression: &str,
+        variables: &S,
+    ) -> Result<Value, EvaluationError>
+    where
+        S: Scope + ?Sized,
+    {
+        let expression = self.compile(expression)?;
+        self.evaluate_expression(&expression, variables)
+    }
+
+    /// Evaluates a compiled expression. Identifiers are looked up in `variables` first and
+    /// then in the evaluator's context, so one evaluator can serve many payloads.
+    pub fn evaluate_expression<S>(
+        &self,
+        expression: &Expression,
+        variables: &S,
+    ) -> Result<Value, EvaluationError>
+    where
+        S: Scope + ?Sized,
+    {
+        self.evaluate_cancellable(expression, variables, &CancellationToken::never())
+    }
+
+    /// Like [`evaluate_expression`](Evaluator::evaluate_expression), but stops with
+    /// [`ErrorKind::Cancelled`] or [`ErrorKind::Timeout`] once `token` is cancelled or its
+    /// deadline passes. The token is checked before each node is evaluated and handed to
+    /// cancellable host functions.
+    pub fn evaluate_cancellable<S>(
+        &self,
+        expression: &Expression,
+        variables: &S,
+        token: &CancellationToken,
+    ) -> Result<Value, EvaluationError>
+    where
+        S: Scope + ?Sized,
+    {
+        let result = self
+            .evaluate_in(expression, &variables, token)
+            .map_err(|e| EvaluationError::new(e, expression.source()))?;
+        Ok(result.into_owned())
+    }
+
+    /// Like [`evaluate_expression`](Evaluator::evaluate_expression), but a result read straight
+    /// from the variables, the context or the expression is returned borrowed instead of
+    /// copied. Only values computed during evaluation are owned.
+    pub fn evaluate_borrowed<'a, S>(
+        &'a self,
+        expression: &'a Expression,
+        variables: &'a S,
+    ) -> Result<Cow<'a, Value>, EvaluationError>
+    where
+        S: Scope + ?Sized,
+    {
+        self.evaluate_in(expression, variables.as_scope(), &CancellationToken::never())
+            .map_err(|e| EvaluationError::new(e, expression.source()))
+    }
+
+    fn evaluate_in<'a>(
+        &'a self,
+        expression: &'a Expression,
+        variables: &'a dyn Scope,
+        token: &CancellationToken,
+    ) -> Result<Cow<'a, Value>, NodeError> {
+        let frame = Frame {
+            source: expression.source(),
+            variables,
+            base: &self.scope,
+            budget: Budget::new(self.limits, token),
+            shared: None,
+        };
+
+        // Values are borrowed from the scopes while evaluating; nothing is deep-copied
+        // unless an operation needs an owned value
+        let result = self.evaluate_node(expression.root(), &frame)?;
 
         #[cfg(feature = "logging")]
         self.logger.trace(&format!("Result: {}", result));
@@ -83,81 +386,99 @@ impl Evaluator {
         Ok(result)
     }
 
-    fn evaluate_node(&self, node: &SyntaxNode) -> Result<Value, NodeError> {
+    fn evaluate_node<'a>(
+        &self,
+        expr: &'a Expr,
+        frame: &Frame<'a>,
+    ) -> Result<Cow<'a, Value>, NodeError> {
         #[cfg(feature = "logging")]
         self.logger.trace(&format!(
-            "Evaluating NodeKind: {:#?}, {:?}",
-            node.kind(),
-            node.to_string()
+            "Evaluating Node: {:?}",
+            &frame.source[expr.span.start..expr.span.end]
         ));
 
-        let res = match node.kind() {
-            SyntaxKind::EXPR_STMT => {
-                let expr = node.first_child().ok_or_else(|| NodeError {
-                    message: "[Empty expression]".to_string(),
-                    node: None,
-                })?;
-                self.evaluate_node(&expr)
-            }
-            SyntaxKind::DOT_EXPR => self.evaluate_dot_expr(&DotExpr::cast(node.clone()).unwrap()),
-            SyntaxKind::NAME_REF => self.evaluate_name_ref(&NameRef::cast(node.clone()).unwrap()),
-            SyntaxKind::NAME => self.evaluate_name(&Name::cast(node.clone()).unwrap()),
-            SyntaxKind::BIN_EXPR => self.evaluate_bin_expr(&BinExpr::cast(node.clone()).unwrap()),
-            SyntaxKind::LITERAL => self.evaluate_literal(&Expr::cast(node.clone()).unwrap()),
-            SyntaxKind::COND_EXPR => {
-                self.evaluate_cond_expr(&CondExpr::cast(node.clone()).unwrap())
-            }
-            SyntaxKind::IDENT => self.evaluate_identifier(&Expr::cast(node.clone()).unwrap()),
-            SyntaxKind::UNARY_EXPR => {
-                self.evaluate_prefix_expr(&UnaryExpr::cast(node.clone()).unwrap())
-            }
-            SyntaxKind::CALL_EXPR => {
-                self.evaluate_call_expr(&CallExpr::cast(node.clone()).unwrap())
-            }
-            SyntaxKind::GROUPING_EXPR => {
-                let expr = node.first_child().ok_or_else(|| NodeError {
-                    message: "[Empty grouping expression]".to_string(),
-                    node: None,
-                })?;
-                self.evaluate_node(&expr)
-            }
-            _ => Err(NodeError {
-                message: format!("Unsupported syntax kind: {:?}", node.kind()),
-                node: Some(node.clone()),
-            }),
+        frame.budget.enter().map_err(|e| e.or_span(expr.span))?;
+
+        let slot = frame.shared.and_then(|shared| shared.slot(expr));
+        if let Some(value) = slot.and_then(|slot| slot.get()) {
+            frame.budget.leave();
+            return Ok(Cow::Borrowed(value.as_ref()));
+        }
+
+        let res = match &expr.kind {
+            ExprKind::Literal(value) => Ok(Cow::Borrowed(value)),
+            ExprKind::Identifier(name) => self.evaluate_by_name(name, frame),
+            ExprKind::Member { .. } => self.evaluate_member(expr, frame),
+            ExprKind::Unary { op, operand } => self
+                .evaluate_unary(*op, operand, frame)
+                .and_then(|value| frame.produced(value)),
+            ExprKind::Binary { op, left, right } => self
+                .evaluate_binary(*op, left, right, frame)
+                .and_then(|value| frame.produced(value)),
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => self.evaluate_conditional(test, consequent, alternate, frame),
+            ExprKind::Call {
+                function,
+                arguments,
+            } => self
+                .evaluate_call(expr, function, arguments, frame)
+                .and_then(|value| frame.produced(value)),
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => self
+                .evaluate_method_call(expr, receiver, method, arguments, frame)
+                .and_then(|value| frame.produced(value)),
+            ExprKind::Unsupported(construct) => Err(NodeError::at(
+                ErrorKind::Unsupported {
+                    construct: construct.clone(),
+                },
+                expr.span,
+            )),
+        }
+        // Errors raised by the operation itself, such as a division by zero, point at this node
+        .map_err(|e| e.or_span(expr.span));
+
+        // Failures aren't shared, their spans point into this expression only
+        let res = match (res, slot) {
+            (Ok(value), Some(slot)) => Ok(Cow::Borrowed(slot.get_or_init(|| value).as_ref())),
+            (res, _) => res,
         };
 
+        frame.budget.leave();
+
         #[cfg(feature = "logging")]
         self.logger.trace(&format!(
-            "NodeKind: {:?} => {:#?}",
-            node.kind(),
+            "Node: {:?} => {:#?}",
+            &frame.source[expr.span.start..expr.span.end],
             res.as_ref()
         ));
 
         res
     }
 
-    fn evaluate_bin_expr(&self, bin_expr: &BinExpr) -> Result<Value, NodeError> {
-        #[cfg(feature = "logging")]
-        self.logger.trace(&format!(
-            "Evaluating Binary Expression: {:#?}",
-            bin_expr.to_string()
-        ));
-
-        let left = bin_expr.lhs().ok_or_else(|| NodeError {
-            message: "[Empty BinExpr Left Expression]".to_string(),
-            node: Some(bin_expr.syntax().clone()),
-        })?;
-        let right = bin_expr.rhs().ok_or_else(|| NodeError {
-            message: "[Empty BinExpr Right Expression]".to_string(),
-            node: Some(bin_expr.syntax().clone()),
-        })?;
-
-        let left_value = self.evaluate_node(left.syntax())?;
-        let right_value = self.evaluate_node(right.syntax())?;
-
-        let op = bin_expr.op_details();
+    fn evaluate_binary<'a>(
+        &self,
+        op: BinaryOp,
+        left: &'a Expr,
+        right: &'a Expr,
+        frame: &Frame<'a>,
+    ) -> Result<Value, NodeError> {
+        let left_value = self.evaluate_node(left, frame)?;
+        let right_value = self.evaluate_node(right, frame)?;
+        self.apply_binary(op, &left_value, &right_value)
+    }
 
+    fn apply_binary(
+        &self,
+        op: BinaryOp,
+        left_value: &Value,
+        right_value: &Value,
+    ) -> Result<Value, NodeError> {
         #[cfg(feature = "logging")]
         self.logger
             .trace(&format!("BinaryOp left_value {:?}", left_value));
@@ -167,42 +488,30 @@ impl Evaluator {
             .trace(&format!("BinaryOp right_value {:?}", right_value));
 
         #[cfg(feature = "logging")]
-        self.logger.trace(&format!("BinaryOp op_details {:?}", op));
+        self.logger.trace(&format!("BinaryOp op {:?}", op));
 
         let result = match op {
-            Some((_, BinOp::Plus)) => self.add_values(left_value, right_value),
-            Some((_, BinOp::Minus)) => self.subtract_values(left_value, right_value),
-            Some((_, BinOp::Times)) => self.multiply_values(left_value, right_value),
-            Some((_, BinOp::Divide)) => self.divide_values(left_value, right_value),
-            Some((_, BinOp::Remainder)) => self.modulo_values(left_value, right_value),
-            Some((_, BinOp::LogicalAnd)) => Ok(Value::Bool(
-                self.to_boolean(&left_value)? && self.to_boolean(&right_value)?,
+            BinaryOp::Add => self.add_values(left_value, right_value),
+            BinaryOp::Sub => self.subtract_values(left_value, right_value),
+            BinaryOp::Mul => self.multiply_values(left_value, right_value),
+            BinaryOp::Div => self.divide_values(left_value, right_value),
+            BinaryOp::Rem => self.modulo_values(left_value, right_value),
+            BinaryOp::And => Ok(Value::Bool(
+                self.to_boolean(left_value)? && self.to_boolean(right_value)?,
             )),
-            Some((_, BinOp::LogicalOr)) => Ok(Value::Bool(
-                self.to_boolean(&left_value)? || self.to_boolean(&right_value)?,
+            BinaryOp::Or => Ok(Value::Bool(
+                self.to_boolean(left_value)? || self.to_boolean(right_value)?,
             )),
-            Some((_, BinOp::Equality)) | Some((_, BinOp::StrictEquality)) => Ok(Value::Bool(
-                self.abstract_equality(&left_value, &right_value),
-            )),
-            Some((_, BinOp::Inequality)) | Some((_, BinOp::StrictInequality)) => Ok(Value::Bool(
-                !self.abstract_equality(&left_value, &right_value),
-            )),
-            Some((_, BinOp::GreaterThan)) => {
-                self.compare_values(&left_value, &right_value, |a, b| a > b)
-            }
-            Some((_, BinOp::LessThan)) => {
-                self.compare_values(&left_value, &right_value, |a, b| a < b)
-            }
-            Some((_, BinOp::GreaterThanOrEqual)) => {
-                self.compare_values(&left_value, &right_value, |a, b| a >= b)
+            BinaryOp::Eq | BinaryOp::StrictEq => {
+                Ok(Value::Bool(self.abstract_equality(left_value, right_value)))
             }
-            Some((_, BinOp::LessThanOrEqual)) => {
-                self.compare_values(&left_value, &right_value, |a, b| a <= b)
-            }
-            _ => Err(NodeError {
-                message: "Unsupported binary operator".to_string(),
-                node: Some(bin_expr.syntax().clone()),
-            }),
+            BinaryOp::NotEq | BinaryOp::StrictNotEq => Ok(Value::Bool(
+                !self.abstract_equality(left_value, right_value),
+            )),
+            BinaryOp::Gt => self.compare_values(op, left_value, right_value, |a, b| a > b),
+            BinaryOp::Lt => self.compare_values(op, left_value, right_value, |a, b| a < b),
+            BinaryOp::GtEq => self.compare_values(op, left_value, right_value, |a, b| a >= b),
+            BinaryOp::LtEq => self.compare_values(op, left_value, right_value, |a, b| a <= b),
         }?;
 
         #[cfg(feature = "logging")]
@@ -211,100 +520,83 @@ impl Evaluator {
         Ok(result)
     }
 
-    fn add_values(&self, left: Value, right: Value) -> Result<Value, NodeError> {
-        match (left.clone(), right.clone()) {
+    fn add_values(&self, left: &Value, right: &Value) -> Result<Value, NodeError> {
+        match (left, right) {
             (Value::Number(l), Value::Number(r)) => {
-                let sum = l.as_f64().unwrap() + r.as_f64().unwrap();
-                Ok(Value::Number(serde_json::Number::from_f64(sum).unwrap()))
+                number("+", l.as_f64().unwrap() + r.as_f64().unwrap())
             }
-            (Value::String(l), Value::String(r)) => Ok(Value::String(l + &r)),
-            (Value::String(l), r) => Ok(Value::String(l + &self.value_to_string(&r))),
-            (l, Value::String(r)) => Ok(Value::String(self.value_to_string(&l) + &r)),
+            (Value::String(l), Value::String(r)) => Ok(Value::String(format!("{}{}", l, r))),
+            (Value::String(l), r) => Ok(Value::String(format!("{}{}", l, self.value_to_string(r)))),
+            (l, Value::String(r)) => Ok(Value::String(self.value_to_string(l) + r)),
             _ => {
                 // Type coercion similar to JavaScript
-                let l_str = self.value_to_string(&left);
-                let r_str = self.value_to_string(&right);
+                let l_str = self.value_to_string(left);
+                let r_str = self.value_to_string(right);
                 Ok(Value::String(l_str + &r_str))
             }
         }
     }
 
-    fn subtract_values(&self, left: Value, right: Value) -> Result<Value, NodeError> {
-        let l_num = self.to_number(&left)?;
-        let r_num = self.to_number(&right)?;
-        Ok(Value::Number(
-            serde_json::Number::from_f64(l_num - r_num).unwrap(),
-        ))
+    fn subtract_values(&self, left: &Value, right: &Value) -> Result<Value, NodeError> {
+        let (l_num, r_num) = self.to_numbers(BinaryOp::Sub, left, right)?;
+        number("-", l_num - r_num)
     }
 
-    fn multiply_values(&self, left: Value, right: Value) -> Result<Value, NodeError> {
-        let l_num = self.to_number(&left)?;
-        let r_num = self.to_number(&right)?;
-        Ok(Value::Number(
-            serde_json::Number::from_f64(l_num * r_num).unwrap(),
-        ))
+    fn multiply_values(&self, left: &Value, right: &Value) -> Result<Value, NodeError> {
+        let (l_num, r_num) = self.to_numbers(BinaryOp::Mul, left, right)?;
+        number("*", l_num * r_num)
     }
 
-    fn divide_values(&self, left: Value, right: Value) -> Result<Value, NodeError> {
-        let l_num = self.to_number(&left)?;
-        let r_num = self.to_number(&right)?;
+    fn divide_values(&self, left: &Value, right: &Value) -> Result<Value, NodeError> {
+        let (l_num, r_num) = self.to_numbers(BinaryOp::Div, left, right)?;
         if r_num == 0.0 {
-            return Err(NodeError {
-                message: "Division by zero".to_string(),
-                node: None,
-            });
+            return Err(ErrorKind::DivisionByZero.into());
         }
-        Ok(Value::Number(
-            serde_json::Number::from_f64(l_num / r_num).unwrap(),
-        ))
+        number("/", l_num / r_num)
     }
 
-    fn modulo_values(&self, left: Value, right: Value) -> Result<Value, NodeError> {
-        let l_num = self.to_number(&left)?;
-        let r_num = self.to_number(&right)?;
-        Ok(Value::Number(
-            serde_json::Number::from_f64(l_num % r_num).unwrap(),
-        ))
+    fn modulo_values(&self, left: &Value, right: &Value) -> Result<Value, NodeError> {
+        let (l_num, r_num) = self.to_numbers(BinaryOp::Rem, left, right)?;
+        number("%", l_num % r_num)
     }
 
-    fn compare_values<F>(&self, left: &Value, right: &Value, cmp: F) -> Result<Value, NodeError>
+    fn compare_values<F>(
+        &self,
+        op: BinaryOp,
+        left: &Value,
+        right: &Value,
+        cmp: F,
+    ) -> Result<Value, NodeError>
     where
         F: Fn(f64, f64) -> bool,
     {
-        let l_num = self.to_number(left)?;
-        let r_num = self.to_number(right)?;
+        let (l_num, r_num) = self.to_numbers(op, left, right)?;
         Ok(Value::Bool(cmp(l_num, r_num)))
     }
 
-    fn evaluate_prefix_expr(&self, prefix_expr: &UnaryExpr) -> Result<Value, NodeError> {
-        #[cfg(feature = "logging")]
-        self.logger.trace(&format!(
-            "Evaluating Prefix Expression: {:#?}",
-            prefix_expr.to_string()
-        ));
-        let expr = prefix_expr.expr().ok_or_else(|| NodeError {
-            message: "[Empty PrefixExpr Expression]".to_string(),
-            node: Some(prefix_expr.syntax().clone()),
-        })?;
-        let expr_value = self.evaluate_node(expr.syntax())?;
-
-        let op = prefix_expr.op_details();
+    fn evaluate_unary<'a>(
+        &self,
+        op: UnaryOp,
+        operand: &'a Expr,
+        frame: &Frame<'a>,
+    ) -> Result<Value, NodeError> {
+        let expr_value = self.evaluate_node(operand, frame)?;
+        self.apply_unary(op, &expr_value)
+    }
 
+    fn apply_unary(&self, op: UnaryOp, expr_value: &Value) -> Result<Value, NodeError> {
         let result = match op {
-            Some((_, UnaryOp::LogicalNot)) => Value::Bool(!self.to_boolean(&expr_value)?),
-            Some((_, UnaryOp::Minus)) => {
-                let num = self.to_number(&expr_value)?;
-                Value::Number(serde_json::Number::from_f64(-num).unwrap())
-            }
-            Some((_, UnaryOp::Plus)) => {
-                let num = self.to_number(&expr_value)?;
-                Value::Number(serde_json::Number::from_f64(num).unwrap())
-            }
-            _ => {
-                return Err(NodeError {
-                    message: "Unsupported unary operator".to_string(),
-                    node: Some(prefix_expr.syntax().clone()),
-                })
+            UnaryOp::Not => Value::Bool(!self.to_boolean(expr_value)?),
+            UnaryOp::Minus | UnaryOp::Plus => {
+                let num = self
+                    .to_number(expr_value)
+                    .ok_or_else(|| ErrorKind::TypeError {
+                        op: op.as_str(),
+                        left: type_name(expr_value),
+                        right: None,
+                    })?;
+                let num = if op == UnaryOp::Minus { -num } else { num };
+                number(op.as_str(), num)?
             }
         };
 
@@ -314,32 +606,20 @@ impl Evaluator {
         Ok(result)
     }
 
-    fn evaluate_cond_expr(&self, cond_expr: &CondExpr) -> Result<Value, NodeError> {
-        #[cfg(feature = "logging")]
-        self.logger.trace(&format!(
-            "Evaluating Conditional Expression: {:#?}",
-            cond_expr.to_string()
-        ));
-        let cond = cond_expr.test().ok_or_else(|| NodeError {
-            message: "[Empty CondExpr Test Expression]".to_string(),
-            node: Some(cond_expr.syntax().clone()),
-        })?;
-        let true_expr = cond_expr.cons().ok_or_else(|| NodeError {
-            message: "[Empty CondExpr Consequent Expression]".to_string(),
-            node: Some(cond_expr.syntax().clone()),
-        })?;
-        let false_expr = cond_expr.alt().ok_or_else(|| NodeError {
-            message: "[Empty CondExpr Alternate Expression]".to_string(),
-            node: Some(cond_expr.syntax().clone()),
-        })?;
-
-        let cond_value = self.evaluate_node(cond.syntax())?;
+    fn evaluate_conditional<'a>(
+        &self,
+        test: &'a Expr,
+        consequent: &'a Expr,
+        alternate: &'a Expr,
+        frame: &Frame<'a>,
+    ) -> Result<Cow<'a, Value>, NodeError> {
+        let cond_value = self.evaluate_node(test, frame)?;
         let cond_bool = self.to_boolean(&cond_value)?;
 
         let result = if cond_bool {
-            self.evaluate_node(true_expr.syntax())?
+            self.evaluate_node(consequent, frame)?
         } else {
-            self.evaluate_node(false_expr.syntax())?
+            self.evaluate_node(alternate, frame)?
         };
 
         #[cfg(feature = "logging")]
@@ -349,81 +629,115 @@ impl Evaluator {
         Ok(result)
     }
 
-    fn evaluate_dot_expr(&self, dot_expr: &DotExpr) -> Result<Value, NodeError> {
+    fn evaluate_member<'a>(
+        &self,
+        expr: &'a Expr,
+        frame: &Frame<'a>,
+    ) -> Result<Cow<'a, Value>, NodeError> {
+        let chain = member_chain(expr);
+
         #[cfg(feature = "logging")]
         self.logger
-            .trace(&format!("Evaluating Dot Expression: {:#?}", dot_expr));
-
-        // Start with the leftmost expression
-        let mut current_expr = dot_expr.clone();
-        let mut property_chain = Vec::new();
-
-        // Collect all identifiers in the dot expression
-        loop {
-            let prop = current_expr.prop();
-            let obj = current_expr.object();
-
-            if let Some(prop) = prop {
-                let prop_name = prop.syntax().text().to_string();
-                property_chain.push(prop_name);
-            } else {
-                return Err(NodeError {
-                    message: "Missing property in dot expression".to_string(),
-                    node: Some(current_expr.syntax().clone()),
-                });
+            .trace(&format!("Property Chain: {:?}", chain.properties));
+
+        match &chain.root.kind {
+            ExprKind::Identifier(name) => self.variable_member(&chain, name, frame),
+            _ => {
+                let object = self.evaluate_node(chain.root, frame)?;
+                self.member_of(&chain, object, frame)
             }
+        }
+    }
 
-            if let Some(obj_expr) = obj {
-                let obj_syntax = obj_expr.syntax().clone();
-                if let Some(prev_dot_expr) = DotExpr::cast(obj_syntax.clone()) {
-                    current_expr = prev_dot_expr;
-                } else if let Some(name_ref) = NameRef::cast(obj_syntax.clone()) {
-                    let obj_name = name_ref.syntax().text().to_string();
-                    property_chain.push(obj_name);
-                    break;
-                } else if let Some(name) = Name::cast(obj_syntax.clone()) {
-                    let obj_name = name.syntax().text().to_string();
-                    property_chain.push(obj_name);
-                    break;
-                } else {
-                    return Err(NodeError {
-                        message: "Unsupported object type in dot expression".to_string(),
-                        node: Some(obj_expr.syntax().clone()),
-                    });
+    /// Reads the properties of `chain` from the variable it starts with. A missing variable
+    /// reads as null, like a missing property.
+    fn variable_member<'a>(
+        &self,
+        chain: &MemberChain<'_>,
+        name: &str,
+        frame: &Frame<'a>,
+    ) -> Result<Cow<'a, Value>, NodeError> {
+        self.check_global(name)
+            .map_err(|e| e.or_span(chain.root.span))?;
+
+        match frame.lookup_path(name, &chain.properties) {
+            Some(value) => {
+                if self.strict_properties && value.is_null() {
+                    self.check_path(chain, name, |path| frame.lookup_path(name, path))?;
                 }
-            } else {
-                break;
+                Ok(value)
             }
+            None if self.strict_properties && !chain.optional[0] => Err(self
+                .undefined_variable(name, frame)
+                .or_span(chain.root.span)),
+            None => Ok(Cow::Owned(Value::Null)),
         }
+    }
 
-        // Reverse the property chain to get the correct order
-        property_chain.reverse();
-
-        #[cfg(feature = "logging")]
-        self.logger
-            .trace(&format!("Property Chain: {:?}", property_chain));
-
-        // Start from the top-level context
-        let mut value = match self.context.get(&property_chain[0]) {
-            Some(ContextEntry::Variable(v)) => v.clone(),
-            _ => Value::Null,
-        };
+    /// Reads the properties of `chain` from `object`, the value of its leftmost expression.
+    fn member_of<'a>(
+        &self,
+        chain: &MemberChain<'_>,
+        object: Cow<'a, Value>,
+        frame: &Frame<'a>,
+    ) -> Result<Cow<'a, Value>, NodeError> {
+        if self.strict_properties {
+            let root = &frame.source[chain.root.span.start..chain.root.span.end];
+            self.check_path(chain, root, |path| {
+                Some(scope::walk(Cow::Borrowed(object.as_ref()), path))
+            })?;
+        }
+        Ok(scope::walk(object, &chain.properties))
+    }
 
-        // Navigate through the nested properties
-        for prop in &property_chain[1..] {
-            match &value {
-                Value::Object(map) => {
-                    value = map.get(prop).cloned().unwrap_or(Value::Null);
-                }
-                _ => {
-                    // Return Null when the value is not an object or property is missing
-                    value = Value::Null;
-                    break;
-                }
+    /// Fails on the first property of `chain` that doesn't exist, unless it is read with
+    /// `?.`. `resolve` yields the value of the root followed by a prefix of the properties.
+    fn check_path<'v>(
+        &self,
+        chain: &MemberChain<'_>,
+        root: &str,
+        resolve: impl Fn(&[&str]) -> Option<Cow<'v, Value>>,
+    ) -> Result<(), NodeError> {
+        for (index, property) in chain.properties.iter().enumerate() {
+            let Some(parent) = resolve(&chain.properties[..index]) else {
+                return Ok(());
+            };
+            let object = parent.as_object();
+            if object.is_some_and(|object| object.contains_key(*property)) {
+                continue;
             }
+            if chain.optional[index] {
+                return Ok(());
+            }
+
+            let path = std::iter::once(root)
+                .chain(chain.properties[..index].iter().copied())
+                .collect::<Vec<_>>()
+                .join(".");
+            let suggestion = object
+                .and_then(|object| {
+                    suggest::did_you_mean(property, object.keys().map(String::as_str))
+                })
+                .map(|candidate| format!("{}.{}", path, candidate));
+            let kind = ErrorKind::MissingProperty {
+                path,
+                property: property.to_string(),
+            };
+            return Err(NodeError::at(kind, chain.spans[index]).with_suggestion(suggestion));
         }
+        Ok(())
+    }
 
-        Ok(value)
+    /// Suggests a sibling key for the first property of a member chain that is missing,
+    /// e.g. `user.name` for `user.nmae`.
+    fn property_suggestion(&self, expr: &Expr, frame: &Frame<'_>) -> Option<String> {
+        let chain = member_chain(expr);
+        let ExprKind::Identifier(name) = &chain.root.kind else {
+            return None;
+        };
+        self.check_path(&chain, name, |path| frame.lookup_path(name, path))
+            .err()?
+            .suggestion
     }
 
     // Implement abstract equality similar to JavaScript
@@ -437,182 +751,197 @@ impl Evaluator {
         }
     }
 
-    fn evaluate_by_name(&self, identifier_name: String) -> Result<Value, NodeError> {
-        let identifier_value = match self.context.get(&identifier_name) {
-            Some(ContextEntry::Variable(v)) => Some(v.clone()),
-            _ => None,
-        };
+    fn evaluate_by_name<'a>(
+        &self,
+        identifier_name: &str,
+        frame: &Frame<'a>,
+    ) -> Result<Cow<'a, Value>, NodeError> {
+        self.check_global(identifier_name)?;
+        let identifier_value = frame.lookup(identifier_name);
 
         #[cfg(feature = "logging")]
         self.logger
             .trace(&format!("Identifier Value: {:#?}", identifier_value));
 
         match identifier_value {
-            Some(value) => Ok(value.clone()),
-            None => Err(NodeError {
-                message: format!("Identifier '{}' not found in context.", identifier_name),
-                node: None,
-            }),
+            Some(value) => Ok(value),
+            None => Err(self.undefined_variable(identifier_name, frame)),
         }
     }
 
-    fn evaluate_name(&self, name: &Name) -> Result<Value, NodeError> {
-        #[cfg(feature = "logging")]
-        self.logger
-            .trace(&format!("Evaluating Name: {:#?}", name.to_string()));
-        let identifier_name = name
-            .ident_token()
-            .ok_or_else(|| NodeError {
-                message: "[Empty Name]".to_string(),
-                node: Some(name.syntax().clone()),
-            })?
-            .to_string();
-
-        self.evaluate_by_name(identifier_name)
+    fn check_global(&self, name: &str) -> Result<(), NodeError> {
+        self.policy
+            .check(Capability::Global, name)
+            .map_err(NodeError::from)
     }
 
-    fn evaluate_name_ref(&self, name_ref: &NameRef) -> Result<Value, NodeError> {
-        #[cfg(feature = "logging")]
-        self.logger.trace(&format!(
-            "Evaluating Name Reference: {:#?}",
-            name_ref.to_string()
-        ));
-        let identifier_name = name_ref
-            .ident_token()
-            .ok_or_else(|| NodeError {
-                message: "[Empty NameRef]".to_string(),
-                node: Some(name_ref.syntax().clone()),
-            })?
-            .to_string();
-
-        self.evaluate_by_name(identifier_name)
+    fn undefined_variable(&self, name: &str, frame: &Frame<'_>) -> NodeError {
+        let mut names = frame.variables.names();
+        names.extend(frame.base.names());
+        names.retain(|name| self.policy.allows(Capability::Global, name));
+        let suggestion = suggest::did_you_mean(name, names).map(str::to_string);
+        NodeError::from(ErrorKind::UndefinedVariable {
+            name: name.to_string(),
+        })
+        .with_suggestion(suggestion)
     }
 
-    fn evaluate_identifier(&self, identifier: &Expr) -> Result<Value, NodeError> {
-        #[cfg(feature = "logging")]
-        self.logger.trace(&format!(
-            "Evaluating Identifier: {:#?}",
-            identifier.to_string()
-        ));
-        let identifier_name = identifier.to_string();
-
-        self.evaluate_by_name(identifier_name)
+    fn evaluate_arguments<'a>(
+        &self,
+        arguments: &'a [Expr],
+        frame: &Frame<'a>,
+    ) -> Result<Vec<Value>, NodeError> {
+        arguments
+            .iter()
+            .map(|arg| self.evaluate_node(arg, frame).map(Cow::into_owned))
+            .collect()
     }
 
-    fn evaluate_literal(&self, literal: &Expr) -> Result<Value, NodeError> {
-        #[cfg(feature = "logging")]
-        self.logger
-            .trace(&format!("Evaluating Literal: {:#?}", literal.to_string()));
-
-        let literal_str = literal.to_string();
-
-        // Handle numeric literals
-        if let Ok(number) = literal_str.parse::<f64>() {
-            return Ok(Value::Number(serde_json::Number::from_f64(number).unwrap()));
-        }
-
-        // Handle string literals
-        if literal_str.starts_with('"') || literal_str.starts_with('\'') {
-            let unquoted = literal_str
-                .trim_matches(|c| c == '"' || c == '\'')
-                .to_string();
-            return Ok(Value::String(unquoted));
-        }
-
-        // Handle boolean literals
-        match literal_str.as_str() {
-            "true" => return Ok(Value::Bool(true)),
-            "false" => return Ok(Value::Bool(false)),
-            "null" => return Ok(Value::Null),
-            _ => {}
-        }
-
-        Err(NodeError {
-            message: format!("Unknown literal type: {}", literal_str),
-            node: Some(literal.syntax().clone()),
-        })
+    fn evaluate_method_call<'a>(
+        &self,
+        expr: &'a Expr,
+        receiver: &'a Expr,
+        method: &str,
+        arguments: &'a [Expr],
+        frame: &Frame<'a>,
+    ) -> Result<Value, NodeError> {
+        self.policy
+            .check(Capability::Method, method)
+            .map_err(|kind| NodeError::at(kind, expr.span))?;
+        let obj_value = self.evaluate_node(receiver, frame)?;
+        self.regexes.policy().check_call(method, arguments)?;
+        let args = self.evaluate_arguments(arguments, frame)?;
+        self.call_method(expr, receiver, method, &obj_value, args, frame)
     }
 
-    fn evaluate_call_expr(&self, expr: &CallExpr) -> Result<Value, NodeError> {
-        let callee = expr.callee().ok_or_else(|| NodeError {
-            message: "Empty callee in call expression".to_string(),
-            node: Some(expr.syntax().clone()),
-        })?;
-        let args = match expr.arguments() {
-            Some(args) => {
-                let args = args.args();
-                let mut arg_values = Vec::new();
-                for arg in args {
-                    let arg_value = self.evaluate_node(arg.syntax())?;
-                    arg_values.push(arg_value);
-                }
-                arg_values
+    /// Calls `method` on `obj_value`, the value of `receiver`, once its arguments are evaluated.
+    fn call_method(
+        &self,
+        expr: &Expr,
+        receiver: &Expr,
+        method: &str,
+        obj_value: &Value,
+        args: Vec<Value>,
+        frame: &Frame<'_>,
+    ) -> Result<Value, NodeError> {
+        let receiver_type = type_name(obj_value);
+        if let Some(signature) = crate::method::signature(receiver_type, method) {
+            if !signature.accepts(args.len()) {
+                let kind = ErrorKind::ArityMismatch {
+                    callee: method.to_string(),
+                    min: signature.min_args,
+                    max: signature.max_args,
+                    found: args.len(),
+                };
+                return Err(NodeError::at(kind, expr.span));
+            }
+        }
+        let result = match obj_value {
+            Value::String(s) => {
+                Self::reserve_str_method(s, method, &args, &frame.budget)
+                    .map_err(|e| e.or_span(expr.span))?;
+                self.str_method(s, method, args)
             }
-            None => vec![],
+            Value::Array(arr) => Self::array_method(arr, method, args),
+            _ => Err(Self::unknown_method(receiver_type, method)),
         };
-        if callee.syntax().kind() == SyntaxKind::DOT_EXPR {
-            let dot_expr = DotExpr::cast(callee.syntax().clone()).ok_or_else(|| NodeError {
-                message: "Invalid callee in call expression".to_string(),
-                node: Some(expr.syntax().clone()),
-            })?;
-            let obj = dot_expr.object().ok_or_else(|| NodeError {
-                message: "Empty object in dot expression".to_string(),
-                node: Some(expr.syntax().clone()),
-            })?;
-            let prop = dot_expr.prop().ok_or_else(|| NodeError {
-                message: "Empty property in dot expression".to_string(),
-                node: Some(expr.syntax().clone()),
-            })?;
-            let obj_value = self.evaluate_node(obj.syntax())?;
-            let prop_name = prop.syntax().text().to_string();
-            let result = match obj_value {
-                Value::String(s) => Self::str_method(&s, &prop_name, args),
-                Value::Array(arr) => Self::array_method(&arr, &prop_name, args),
-                _ => {
-                    return Err(NodeError {
-                        message: format!(
-                            "Unsupported object type for method call: {}",
-                            obj_value.to_string()
-                        ),
-                        node: Some(expr.syntax().clone()),
-                    })
+        result.map_err(|kind| {
+            let suggestion = match &kind {
+                // Calling a method on null usually means a misspelt property on the way
+                ErrorKind::UnknownMethod { .. } if receiver_type == "null" => {
+                    self.property_suggestion(receiver, frame)
                 }
+                ErrorKind::UnknownMethod { .. } => {
+                    suggest::method(receiver_type, method, &self.policy)
+                }
+                _ => None,
             };
-            return result.map_err(|e| NodeError {
-                message: format!(
-                    "Error calling method '{}' on object: {}. {}",
-                    prop_name,
-                    obj.text().to_string(),
-                    e
-                ),
-                node: Some(expr.syntax().clone()),
-            });
-        }
-        let func = match self.context.get(&callee.to_string()) {
-            Some(ContextEntry::Function(f)) => f,
-            _ => {
-                return Err(NodeError {
-                    message: format!("Function '{}' not found in context", callee.to_string()),
-                    node: Some(expr.syntax().clone()),
+            NodeError::at(kind, expr.span).with_suggestion(suggestion)
+        })
+    }
+
+    fn evaluate_call<'a>(
+        &self,
+        expr: &'a Expr,
+        function: &str,
+        arguments: &'a [Expr],
+        frame: &Frame<'a>,
+    ) -> Result<Value, NodeError> {
+        self.policy
+            .check(Capability::Function, function)
+            .map_err(|kind| NodeError::at(kind, expr.span))?;
+        let args = self.evaluate_arguments(arguments, frame)?;
+        self.call_function(expr, function, args, frame)
+    }
+
+    /// Calls the host function `function` once its arguments are evaluated.
+    fn call_function(
+        &self,
+        expr: &Expr,
+        function: &str,
+        args: Vec<Value>,
+        frame: &Frame<'_>,
+    ) -> Result<Value, NodeError> {
+        match self.functions.get(function) {
+            Some(f) => {
+                if let Some(arity) = f.arity().filter(|&arity| arity != args.len()) {
+                    let kind = ErrorKind::ArityMismatch {
+                        callee: function.to_string(),
+                        min: arity,
+                        max: arity,
+                        found: args.len(),
+                    };
+                    return Err(NodeError::at(kind, expr.span));
+                }
+                let result = f.call_with_token(args, frame.budget.token());
+                // A function that gave up because it was cancelled fails as cancelled, and one
+                // that ran past the deadline doesn't get to return late
+                frame
+                    .budget
+                    .check_token()
+                    .map_err(|e| e.or_span(expr.span))?;
+                result.map_err(|source| {
+                    let kind = ErrorKind::HostFunction {
+                        name: function.to_string(),
+                        source,
+                    };
+                    NodeError::at(kind, expr.span)
                 })
             }
-        };
-        Ok(func(args))
+            None => {
+                let suggestion = suggest::function(function, self.functions.keys(), &self.policy);
+                let kind = ErrorKind::UndefinedFunction {
+                    name: function.to_string(),
+                };
+                Err(NodeError::at(kind, expr.span).with_suggestion(suggestion))
+            }
+        }
     }
 
-    fn to_number(&self, value: &Value) -> Result<f64, NodeError> {
+    fn to_number(&self, value: &Value) -> Option<f64> {
         match value {
-            Value::Number(n) => Ok(n.as_f64().unwrap()),
-            Value::String(s) => s.parse::<f64>().map_err(|_| NodeError {
-                message: format!("Cannot convert string '{}' to number", s),
-                node: None,
-            }),
-            Value::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
-            Value::Null => Ok(0.0),
-            _ => Err(NodeError {
-                message: "Cannot convert value to number".to_string(),
-                node: None,
-            }),
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.parse::<f64>().ok(),
+            Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            Value::Null => Some(0.0),
+            _ => None,
+        }
+    }
+
+    fn to_numbers(
+        &self,
+        op: BinaryOp,
+        left: &Value,
+        right: &Value,
+    ) -> Result<(f64, f64), NodeError> {
+        match (self.to_number(left), self.to_number(right)) {
+            (Some(l_num), Some(r_num)) => Ok((l_num, r_num)),
+            _ => Err(ErrorKind::TypeError {
+                op: op.as_str(),
+                left: type_name(left),
+                right: Some(type_name(right)),
+            }
+            .into()),
         }
     }
 
@@ -642,7 +971,16 @@ impl Evaluator {
         }
     }
 
-    fn str_method(value: &str, method: &str, args: Vec<Value>) -> Result<Value> {
+    fn str_method(&self, value: &str, method: &str, args: Vec<Value>) -> Result<Value, ErrorKind> {
+        if method == "regexReplace" {
+            // Patterns go through the cache, which compiles them within the regex policy
+            let pattern = args.first().and_then(Value::as_str).unwrap_or("");
+            let re = self.regexes.get(method, pattern)?;
+            return StrMethod::new(args)
+                .regex_replace(value, &re)
+                .map_err(|e| Self::invalid_arguments(method, e));
+        }
+
         let str_method = StrMethod::new(args);
         let result = match method {
             "replace" => str_method.replace(value),
@@ -655,24 +993,118 @@ impl Evaluator {
             "substring" => str_method.substring(value),
             "startsWith" => str_method.starts_with(value),
             "endsWith" => str_method.ends_with(value),
-            "regexReplace" => str_method.regex_replace(value),
             "length" => str_method.length(value),
             "trim" => str_method.trim(value),
-            _ => {
-                bail!("Unknown string method: {}", method);
-            }
+            _ => return Err(Self::unknown_method("string", method)),
         };
-        result
+        result.map_err(|e| Self::invalid_arguments(method, e))
     }
 
-    fn array_method(value: &Vec<Value>, method: &str, args: Vec<Value>) -> Result<Value> {
+    fn array_method(value: &[Value], method: &str, args: Vec<Value>) -> Result<Value, ErrorKind> {
         let array_method = ArrayMethod::new(args);
         let result = match method {
             "join" => array_method.join(value),
-            _ => {
-                bail!("Unknown array method: {}", method);
-            }
+            _ => return Err(Self::unknown_method("array", method)),
         };
-        result
+        result.map_err(|e| Self::invalid_arguments(method, e))
+    }
+
+    /// Fails string methods whose result would exceed the limits before it is built.
+    fn reserve_str_method(
+        value: &str,
+        method: &str,
+        args: &[Value],
+        budget: &Budget,
+    ) -> Result<(), NodeError> {
+        let arg = |index: usize| args.get(index).and_then(Value::as_str).unwrap_or("");
+        match method {
+            "replace" => {
+                let (old, new) = (arg(0), arg(1));
+                let len = if old.is_empty() {
+                    value.len() + (value.chars().count() + 1) * new.len()
+                } else {
+                    let count = value.matches(old).count();
+                    value.len() - count * old.len() + count * new.len()
+                };
+                budget.reserve_string(len)
+            }
+            "split" => {
+                let pieces = match arg(0) {
+                    "" => value.chars().count(),
+                    delimiter => value.matches(delimiter).count() + 1,
+                };
+                budget.reserve_array(pieces)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn unknown_method(receiver_type: &'static str, method: &str) -> ErrorKind {
+        ErrorKind::UnknownMethod {
+            receiver_type,
+            method: method.to_string(),
+        }
+    }
+
+    fn invalid_arguments(method: &str, error: anyhow::Error) -> ErrorKind {
+        ErrorKind::InvalidArguments {
+            method: method.to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// A member expression such as `a.b?.c`, split into its leftmost expression (`a`) and the
+/// properties read from it (`["b", "c"]`).
+#[derive(Debug, Clone)]
+struct MemberChain<'a> {
+    root: &'a Expr,
+    properties: Vec<&'a str>,
+    /// Whether each property is read with `?.`.
+    optional: Vec<bool>,
+    /// Span of the expression reading up to and including each property.
+    spans: Vec<Span>,
+}
+
+fn member_chain(expr: &Expr) -> MemberChain<'_> {
+    let mut chain = MemberChain {
+        root: expr,
+        properties: Vec::new(),
+        optional: Vec::new(),
+        spans: Vec::new(),
+    };
+    while let ExprKind::Member {
+        object,
+        property,
+        optional,
+    } = &chain.root.kind
+    {
+        chain.properties.push(property.as_str());
+        chain.optional.push(*optional);
+        chain.spans.push(chain.root.span);
+        chain.root = object;
+    }
+    chain.properties.reverse();
+    chain.optional.reverse();
+    chain.spans.reverse();
+    chain
+}
+
+/// Wraps the result of arithmetic, failing on NaN and infinities, which JSON can't hold.
+fn number(op: &'static str, n: f64) -> Result<Value, NodeError> {
+    serde_json::Number::from_f64(n)
+        .map(Value::Number)
+        .ok_or_else(|| ErrorKind::NonFiniteNumber { op }.into())
+}
+
+/// Name of the JSON type of `value`, as used in error messages.
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
     }
 }
@@ -0,0 +1,238 @@
+use serde_json::Value;
+use std::cell::Cell;
+use std::fmt;
+use std::time::Duration;
+
+use crate::cancel::CancellationToken;
+use crate::error::{ErrorKind, NodeError};
+use crate::parse::{tokenize, TokenKind};
+
+/// Bounds on the work a single evaluation may do, for expressions from untrusted sources.
+///
+/// Each limit fails the evaluation with [`ErrorKind::LimitExceeded`] naming the [`Limit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvaluationLimits {
+    /// Deepest nesting of an expression, checked before it is parsed so that deeply nested
+    /// input can't overflow the stack.
+    pub max_depth: usize,
+    /// Most nodes evaluated in one evaluation.
+    pub max_steps: usize,
+    /// Longest string, in bytes, an operation may produce.
+    pub max_string_length: usize,
+    /// Longest array an operation may produce.
+    pub max_array_length: usize,
+    /// Most bytes the values produced during one evaluation may take in total.
+    pub max_allocation: usize,
+    /// Longest one evaluation may run, including time spent in host functions. Fails with
+    /// [`ErrorKind::Timeout`] rather than [`ErrorKind::LimitExceeded`].
+    pub timeout: Option<Duration>,
+}
+
+impl Default for EvaluationLimits {
+    fn default() -> Self {
+        EvaluationLimits {
+            max_depth: 128,
+            max_steps: 100_000,
+            max_string_length: 1 << 20,
+            max_array_length: 100_000,
+            max_allocation: 64 << 20,
+            timeout: None,
+        }
+    }
+}
+
+/// A limit from [`EvaluationLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Limit {
+    Depth,
+    Steps,
+    StringLength,
+    ArrayLength,
+    Allocation,
+    /// Compiled size of a regular expression, from [`RegexPolicy`](crate::RegexPolicy).
+    RegexSize,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Limit::Depth => "depth",
+            Limit::Steps => "step",
+            Limit::StringLength => "string length",
+            Limit::ArrayLength => "array length",
+            Limit::Allocation => "allocation",
+            Limit::RegexSize => "regex size",
+        };
+        f.write_str(name)
+    }
+}
+
+fn exceeded(limit: Limit, max: usize) -> NodeError {
+    ErrorKind::LimitExceeded { limit, max }.into()
+}
+
+/// Rejects `source` if its syntax tree may nest deeper than `max_depth`, without parsing it.
+///
+/// The estimate counts, for each open bracket, the operators read inside it so far, since
+/// a chain such as `a + b + c` nests one level per operator. It never underestimates, so
+/// the parser and everything walking the tree afterwards stay within the limit. The source
+/// is read as the parser's tokens, so strings and comments hide brackets only where they
+/// hide them from the parser too.
+pub(crate) fn check_depth(source: &str, max_depth: usize) -> Result<(), NodeError> {
+    // Operators seen in each open bracket, outermost first
+    let mut levels = vec![0usize];
+    let mut depth = 0usize;
+
+    for token in tokenize(source).0 {
+        match token.kind {
+            TokenKind::Punct("(" | "[" | "{") => {
+                levels.push(0);
+                depth += 1;
+            }
+            TokenKind::Punct(")" | "]" | "}") if levels.len() > 1 => {
+                depth -= levels.pop().unwrap() + 1;
+            }
+            // Arguments and elements are siblings, not nested in one another
+            TokenKind::Punct(",") => {
+                let level = levels.last_mut().unwrap();
+                depth -= *level;
+                *level = 0;
+            }
+            TokenKind::Punct(")" | "]" | "}" | ";") => {}
+            TokenKind::Punct(_) => {
+                *levels.last_mut().unwrap() += 1;
+                depth += 1;
+            }
+            // Keyword operators such as `typeof` or `in` nest like symbols do
+            TokenKind::Name
+                if matches!(
+                    &source[token.span.start..token.span.end],
+                    "typeof" | "void" | "delete" | "in" | "instanceof" | "new" | "await"
+                ) =>
+            {
+                *levels.last_mut().unwrap() += 1;
+                depth += 1;
+            }
+            _ => {}
+        }
+
+        // Plus one for the operand at the bottom
+        if depth + 1 > max_depth {
+            return Err(exceeded(Limit::Depth, max_depth).or_span(token.span));
+        }
+    }
+
+    Ok(())
+}
+
+/// Work done so far by one evaluation, checked against its limits.
+pub(crate) struct Budget {
+    limits: EvaluationLimits,
+    token: CancellationToken,
+    steps: Cell<usize>,
+    depth: Cell<usize>,
+    allocated: Cell<usize>,
+}
+
+impl Budget {
+    /// Starts a budget for an evaluation that stops when `token` says so, or once
+    /// [`EvaluationLimits::timeout`] has passed.
+    pub(crate) fn new(limits: EvaluationLimits, token: &CancellationToken) -> Self {
+        let token = match limits.timeout {
+            Some(timeout) => token.with_timeout(timeout),
+            None => token.clone(),
+        };
+        Budget {
+            limits,
+            token,
+            steps: Cell::new(0),
+            depth: Cell::new(0),
+            allocated: Cell::new(0),
+        }
+    }
+
+    /// Accounts for evaluating one more node, one level deeper than its parent. Pair with
+    /// [`leave`](Budget::leave).
+    pub(crate) fn enter(&self) -> Result<(), NodeError> {
+        self.check_token()?;
+
+        let steps = self.steps.get() + 1;
+        if steps > self.limits.max_steps {
+            return Err(exceeded(Limit::Steps, self.limits.max_steps));
+        }
+        self.steps.set(steps);
+
+        let depth = self.depth.get() + 1;
+        if depth > self.limits.max_depth {
+            return Err(exceeded(Limit::Depth, self.limits.max_depth));
+        }
+        self.depth.set(depth);
+        Ok(())
+    }
+
+    pub(crate) fn leave(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+
+    /// The token host functions are given, expiring with the evaluation.
+    pub(crate) fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Fails if the evaluation was cancelled or has run past its deadline.
+    pub(crate) fn check_token(&self) -> Result<(), NodeError> {
+        self.token.check().map_err(NodeError::from)
+    }
+
+    /// Checks that a string of `len` bytes may be produced, before building it.
+    pub(crate) fn reserve_string(&self, len: usize) -> Result<(), NodeError> {
+        if len > self.limits.max_string_length {
+            return Err(exceeded(Limit::StringLength, self.limits.max_string_length));
+        }
+        Ok(())
+    }
+
+    /// Checks that an array of `len` items may be produced, before building it.
+    pub(crate) fn reserve_array(&self, len: usize) -> Result<(), NodeError> {
+        if len > self.limits.max_array_length {
+            return Err(exceeded(Limit::ArrayLength, self.limits.max_array_length));
+        }
+        Ok(())
+    }
+
+    /// Accounts for a value produced by an operation, checking the lengths of the strings and
+    /// arrays it holds and the total allocated so far.
+    pub(crate) fn account(&self, value: &Value) -> Result<(), NodeError> {
+        let mut size = 0;
+        let mut pending = vec![value];
+
+        while let Some(value) = pending.pop() {
+            size += std::mem::size_of::<Value>();
+            match value {
+                Value::String(s) => {
+                    self.reserve_string(s.len())?;
+                    size += s.len();
+                }
+                Value::Array(items) => {
+                    self.reserve_array(items.len())?;
+                    pending.extend(items);
+                }
+                Value::Object(map) => {
+                    for (key, item) in map {
+                        size += key.len();
+                        pending.push(item);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let allocated = self.allocated.get().saturating_add(size);
+        if allocated > self.limits.max_allocation {
+            return Err(exceeded(Limit::Allocation, self.limits.max_allocation));
+        }
+        self.allocated.set(allocated);
+        Ok(())
+    }
+}
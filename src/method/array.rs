@@ -1,6 +1,10 @@
 use anyhow::{bail, Result};
 use serde_json::Value;
 
+use super::MethodSignature;
+
+pub const METHODS: &[MethodSignature] = &[MethodSignature::new("join", 1, "string")];
+
 pub struct ArrayMethod {
     args: Vec<Value>,
 }
@@ -9,7 +13,7 @@ impl ArrayMethod {
     pub fn new(args: Vec<Value>) -> Self {
         ArrayMethod { args }
     }
-    pub fn join(&self, arr: &Vec<Value>) -> Result<Value> {
+    pub fn join(&self, arr: &[Value]) -> Result<Value> {
         if self.args.len() != 1 {
             bail!("join method requires 1 argument")
         }
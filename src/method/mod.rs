@@ -1,2 +1,43 @@
 pub mod array;
 pub mod str;
+
+/// Name, accepted number of arguments and result type of a built-in method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodSignature {
+    pub name: &'static str,
+    pub min_args: usize,
+    pub max_args: usize,
+    /// JSON type of the result, e.g. `"string"`.
+    pub returns: &'static str,
+}
+
+impl MethodSignature {
+    pub const fn new(name: &'static str, args: usize, returns: &'static str) -> Self {
+        MethodSignature {
+            name,
+            min_args: args,
+            max_args: args,
+            returns,
+        }
+    }
+
+    pub fn accepts(&self, args: usize) -> bool {
+        (self.min_args..=self.max_args).contains(&args)
+    }
+}
+
+/// Built-in methods of values of `receiver_type` (`"string"` or `"array"`).
+pub fn methods(receiver_type: &str) -> &'static [MethodSignature] {
+    match receiver_type {
+        "string" => str::METHODS,
+        "array" => array::METHODS,
+        _ => &[],
+    }
+}
+
+/// Looks up the built-in method `name` of values of `receiver_type`.
+pub fn signature(receiver_type: &str, name: &str) -> Option<&'static MethodSignature> {
+    methods(receiver_type)
+        .iter()
+        .find(|method| method.name == name)
+}
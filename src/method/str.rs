@@ -1,7 +1,30 @@
-use anyhow::{anyhow, bail, Result};
+use anyhow::{bail, Result};
 use regex::Regex;
 use serde_json::Value;
 
+use super::MethodSignature;
+
+pub const METHODS: &[MethodSignature] = &[
+    MethodSignature::new("replace", 2, "string"),
+    MethodSignature::new("contains", 1, "boolean"),
+    MethodSignature::new("split", 1, "array"),
+    MethodSignature::new("indexOf", 1, "number"),
+    MethodSignature::new("lastIndexOf", 1, "number"),
+    MethodSignature::new("toUpperCase", 0, "string"),
+    MethodSignature::new("toLowerCase", 0, "string"),
+    MethodSignature {
+        name: "substring",
+        min_args: 0,
+        max_args: 2,
+        returns: "string",
+    },
+    MethodSignature::new("startsWith", 1, "boolean"),
+    MethodSignature::new("endsWith", 1, "boolean"),
+    MethodSignature::new("regexReplace", 2, "string"),
+    MethodSignature::new("length", 0, "number"),
+    MethodSignature::new("trim", 0, "string"),
+];
+
 pub struct StrMethod {
     args: Vec<Value>,
 }
@@ -63,49 +86,46 @@ impl StrMethod {
         Ok(Value::Number(result.into()))
     }
     pub fn to_upper_case(&self, s: &str) -> Result<Value> {
-        if self.args.len() != 0 {
+        if !self.args.is_empty() {
             bail!("to_upper_case method requires no arguments")
         }
         let result = s.to_uppercase();
         Ok(Value::String(result))
     }
     pub fn to_lower_case(&self, s: &str) -> Result<Value> {
-        if self.args.len() != 0 {
+        if !self.args.is_empty() {
             bail!("to_lower_case method requires no arguments")
         }
         let result = s.to_lowercase();
         Ok(Value::String(result))
     }
     pub fn substring(&self, s: &str) -> Result<Value> {
-        let result = match self.args.len() {
-            1 => {
-                let start = match &self.args[0] {
-                    Value::Number(num) => num.as_f64().unwrap_or(0.0) as usize,
-                    Value::String(s) => s.parse::<usize>().unwrap_or(0),
-                    _ => bail!("substring method requires a number as the first argument"),
-                };
-                let end = s.len();
-                let result = &s[start..end];
-                result.to_string()
-            }
-            2 => {
-                let start = match &self.args[0] {
-                    Value::Number(num) => num.as_f64().unwrap_or(0.0) as usize,
-                    Value::String(s) => s.parse::<usize>().unwrap_or(0),
-                    _ => bail!("substring method requires a number as the first argument"),
-                };
-                let end = match &self.args[1] {
-                    Value::Number(num) => num.as_f64().unwrap_or(0.0) as usize,
-                    Value::String(s) => s.parse::<usize>().unwrap_or(0),
-                    _ => bail!("substring method requires a number as the second argument"),
-                };
-                let result = &s[start..end];
-                result.to_string()
-            }
-            0 => "".to_string(),
+        let index = |arg: &Value, position: &str| match arg {
+            Value::Number(num) => Ok(num.as_f64().unwrap_or(0.0) as usize),
+            Value::String(s) => Ok(s.parse::<usize>().unwrap_or(0)),
+            _ => bail!(
+                "substring method requires a number as the {} argument",
+                position
+            ),
+        };
+        let len = s.len();
+        let (start, end) = match self.args.len() {
+            0 => return Ok(Value::String("".to_string())),
+            1 => (index(&self.args[0], "first")?, len),
+            2 => (
+                index(&self.args[0], "first")?,
+                index(&self.args[1], "second")?,
+            ),
             _ => bail!("substring method requires 1 or 2 arguments"),
         };
-        Ok(Value::String(result))
+        // Indices are byte offsets, as returned by indexOf. Like JavaScript, indices past the
+        // end are clamped and swapped indices are reordered
+        let (start, end) = (start.min(len), end.min(len));
+        let (start, end) = (start.min(end), start.max(end));
+        match s.get(start..end) {
+            Some(result) => Ok(Value::String(result.to_string())),
+            None => bail!("substring index is not on a character boundary"),
+        }
     }
     pub fn starts_with(&self, s: &str) -> Result<Value> {
         if self.args.len() != 1 {
@@ -123,25 +143,25 @@ impl StrMethod {
         let result = s.ends_with(suffix);
         Ok(Value::Bool(result))
     }
-    pub fn regex_replace(&self, s: &str) -> Result<Value> {
+    /// Replaces every match of `re`, the pattern from the first argument compiled by the
+    /// caller, with the second argument.
+    pub fn regex_replace(&self, s: &str, re: &Regex) -> Result<Value> {
         if self.args.len() != 2 {
             bail!("regex_replace method requires 2 arguments")
         }
-        let pattern = self.args[0].as_str().unwrap_or("");
         let replacement = self.args[1].as_str().unwrap_or("");
-        let re = Regex::new(pattern).map_err(|e| anyhow!(e))?;
         let result = re.replace_all(s, replacement);
         Ok(Value::String(result.to_string()))
     }
     pub fn length(&self, s: &str) -> Result<Value> {
-        if self.args.len() != 0 {
+        if !self.args.is_empty() {
             bail!("length method requires no arguments")
         }
         let result = s.chars().count();
         Ok(Value::Number(result.into()))
     }
     pub fn trim(&self, s: &str) -> Result<Value> {
-        if self.args.len() != 0 {
+        if !self.args.is_empty() {
             bail!("trim method requires no arguments")
         }
         let result = s.trim();
@@ -0,0 +1,381 @@
+use serde_json::Value;
+
+use crate::ast::{BinaryOp, Expr, ExprKind, Span, UnaryOp};
+use crate::cancel::CancellationToken;
+use crate::expression::Expression;
+use crate::limits::Budget;
+use crate::policy::Capability;
+use crate::scope::Scope;
+use crate::validate::static_type;
+use crate::{member_chain, Evaluator, Frame};
+
+/// What folding may assume about the expression being simplified.
+pub(crate) struct Folding<'a> {
+    pub(crate) source: &'a str,
+    /// Variables whose values are known. Anything else may be given when evaluating.
+    pub(crate) known: &'a dyn Scope,
+    /// Whether an operand that can't change the result of `&&` or `||`, only make it fail,
+    /// may be dropped. The result is then only the value when evaluating doesn't fail.
+    pub(crate) drop_fallible: bool,
+}
+
+impl Evaluator {
+    /// Simplifies a compiled expression without changing what it evaluates to.
+    ///
+    /// Sub-expressions built only from literals are evaluated once, with this evaluator's
+    /// semantics, and replaced by their result; those that would fail are kept so the
+    /// error is still raised when evaluating. Conditionals with a constant test lose their
+    /// dead branch, and `&&` and `||` drop operands that can't affect the result, such as
+    /// the `true` in `true && x > 600`. Host functions are never called.
+    ///
+    /// The result still reports errors against the original source. Its `Display` prints
+    /// the simplified expression.
+    pub fn optimize(&self, expression: &Expression) -> Expression {
+        let folding = Folding {
+            source: expression.source(),
+            known: &Value::Null,
+            drop_fallible: false,
+        };
+        let root = self.fold(expression.root(), &folding);
+        Expression::new(expression.source(), root)
+    }
+
+    pub(crate) fn fold(&self, expr: &Expr, folding: &Folding<'_>) -> Expr {
+        let kind = match &expr.kind {
+            ExprKind::Literal(_) | ExprKind::Unsupported(_) => return expr.clone(),
+            ExprKind::Identifier(name) => {
+                return self
+                    .resolve_known(expr, name, &[], folding)
+                    .unwrap_or_else(|| expr.clone())
+            }
+            ExprKind::Member {
+                object,
+                property,
+                optional,
+            } => {
+                let chain = member_chain(expr);
+                if let ExprKind::Identifier(name) = &chain.root.kind {
+                    return self
+                        .resolve_known(expr, name, &chain.properties, folding)
+                        .unwrap_or_else(|| expr.clone());
+                }
+                ExprKind::Member {
+                    object: Box::new(self.fold(object, folding)),
+                    property: property.clone(),
+                    optional: *optional,
+                }
+            }
+            ExprKind::Unary { op, operand } => {
+                let operand = self.fold(operand, folding);
+                // `!!x` is `x` when `x` is a boolean already
+                if let (
+                    UnaryOp::Not,
+                    ExprKind::Unary {
+                        op: UnaryOp::Not,
+                        operand: inner,
+                    },
+                ) = (op, &operand.kind)
+                {
+                    if static_type(inner) == Some("boolean") {
+                        return (**inner).clone();
+                    }
+                }
+                ExprKind::Unary {
+                    op: *op,
+                    operand: Box::new(operand),
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let left = self.fold(left, folding);
+                let right = self.fold(right, folding);
+                if let Some(simplified) =
+                    self.simplify_logical(*op, &left, &right, expr.span, folding)
+                {
+                    return simplified;
+                }
+                ExprKind::Binary {
+                    op: *op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }
+            }
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => {
+                let test = self.fold(test, folding);
+                if let ExprKind::Literal(value) = &test.kind {
+                    // Only the branch taken is ever evaluated; a test that doesn't convert is
+                    // left for evaluation to report
+                    if let Ok(taken) = self.to_boolean(value) {
+                        let branch = if taken { consequent } else { alternate };
+                        return self.fold(branch, folding);
+                    }
+                }
+                ExprKind::Conditional {
+                    test: Box::new(test),
+                    consequent: Box::new(self.fold(consequent, folding)),
+                    alternate: Box::new(self.fold(alternate, folding)),
+                }
+            }
+            ExprKind::Call {
+                function,
+                arguments,
+            } => ExprKind::Call {
+                function: function.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|arg| self.fold(arg, folding))
+                    .collect(),
+            },
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => ExprKind::MethodCall {
+                receiver: Box::new(self.fold(receiver, folding)),
+                method: method.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|arg| self.fold(arg, folding))
+                    .collect(),
+            },
+        };
+
+        let mut folded = Expr {
+            kind,
+            span: expr.span,
+        };
+        if let Some(constant) = self.evaluate_constant(&folded, folding) {
+            return constant;
+        }
+        restore_non_literals(&mut folded, expr);
+        folded
+    }
+
+    /// Replaces a variable, or a property path read from one, by its value when `name` is
+    /// known. Reads that fail, such as forbidden globals, are left to fail when evaluating.
+    fn resolve_known(
+        &self,
+        expr: &Expr,
+        name: &str,
+        properties: &[&str],
+        folding: &Folding<'_>,
+    ) -> Option<Expr> {
+        folding.known.get_path(name, properties)?;
+        let value = self.evaluate_with_known(expr, folding)?;
+        Some(Expr {
+            kind: ExprKind::Literal(value),
+            span: expr.span,
+        })
+    }
+
+    fn evaluate_with_known(&self, expr: &Expr, folding: &Folding<'_>) -> Option<Value> {
+        let frame = Frame {
+            source: folding.source,
+            variables: folding.known,
+            base: &Value::Null,
+            budget: Budget::new(self.limits, &CancellationToken::never()),
+            shared: None,
+        };
+        self.evaluate_node(expr, &frame)
+            .ok()
+            .map(|value| value.into_owned())
+    }
+
+    /// Evaluates a node whose operands are all literals, unless it calls a host function,
+    /// fails, or yields an array or object, which can't be written as a literal.
+    fn evaluate_constant(&self, expr: &Expr, folding: &Folding<'_>) -> Option<Expr> {
+        let operands_constant = match &expr.kind {
+            ExprKind::Member { object, .. }
+            | ExprKind::Unary {
+                operand: object, ..
+            } => is_literal(object),
+            ExprKind::Binary { left, right, .. } => is_literal(left) && is_literal(right),
+            ExprKind::MethodCall {
+                receiver,
+                arguments,
+                ..
+            } => is_literal(receiver) && arguments.iter().all(is_literal),
+            _ => false,
+        };
+        if !operands_constant {
+            return None;
+        }
+
+        let value = self.evaluate_with_known(expr, folding)?;
+        if value.is_array() || value.is_object() {
+            return None;
+        }
+        Some(Expr {
+            kind: ExprKind::Literal(value),
+            span: expr.span,
+        })
+    }
+
+    /// Simplifies `&&` and `||` with a constant operand. Both operands are always
+    /// evaluated, so the other operand is only dropped when it can't fail, unless
+    /// `drop_fallible` is set, and only kept alone when it is a boolean already.
+    fn simplify_logical(
+        &self,
+        op: BinaryOp,
+        left: &Expr,
+        right: &Expr,
+        span: Span,
+        folding: &Folding<'_>,
+    ) -> Option<Expr> {
+        let absorbing = match op {
+            BinaryOp::And => false,
+            BinaryOp::Or => true,
+            _ => return None,
+        };
+        for (constant, other) in [(left, right), (right, left)] {
+            let ExprKind::Literal(value) = &constant.kind else {
+                continue;
+            };
+            if self.to_boolean(value).ok()? == absorbing {
+                // `false && x` is false and `true || x` is true, whatever `x` is
+                if folding.drop_fallible || self.is_infallible(other) {
+                    return Some(Expr {
+                        kind: ExprKind::Literal(Value::Bool(absorbing)),
+                        span,
+                    });
+                }
+            } else if static_type(other) == Some("boolean") {
+                // `true && x` and `false || x` are `x`
+                return Some(other.clone());
+            }
+        }
+        None
+    }
+
+    /// Whether evaluating `expr` can never fail, so leaving it out changes nothing.
+    fn is_infallible(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Literal(_) => true,
+            // A missing variable or property reads as null, unless strict properties apply
+            ExprKind::Member { object, .. } if !self.strict_properties => {
+                let mut root = &**object;
+                while let ExprKind::Member { object, .. } = &root.kind {
+                    root = object;
+                }
+                match &root.kind {
+                    ExprKind::Identifier(name) => self.policy.allows(Capability::Global, name),
+                    _ => self.is_infallible(root),
+                }
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => self.is_infallible(operand),
+            ExprKind::Binary {
+                op:
+                    BinaryOp::And
+                    | BinaryOp::Or
+                    | BinaryOp::Eq
+                    | BinaryOp::NotEq
+                    | BinaryOp::StrictEq
+                    | BinaryOp::StrictNotEq,
+                left,
+                right,
+            } => self.is_infallible(left) && self.is_infallible(right),
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => {
+                self.is_infallible(test)
+                    && self.is_infallible(consequent)
+                    && self.is_infallible(alternate)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn is_literal(expr: &Expr) -> bool {
+    matches!(expr.kind, ExprKind::Literal(_))
+}
+
+/// Puts back the original of each operand of `folded` that folded to a value the parser
+/// can't read back. Known variables holding one are inlined so that what is built from them
+/// can fold, but an operand left over reads its variable again. Arrays and objects have no
+/// literal, and an integer parses back as a float, which only operators other than `+`
+/// treat the same, since adding a string prints the two differently.
+fn restore_non_literals(folded: &mut Expr, original: &Expr) {
+    let restore = |folded: &mut Expr, original: &Expr, as_number: bool| {
+        let readable = match &folded.kind {
+            ExprKind::Literal(Value::Array(_) | Value::Object(_)) => false,
+            ExprKind::Literal(Value::Number(n)) => as_number || n.is_f64(),
+            _ => true,
+        };
+        if !readable {
+            *folded = original.clone();
+        }
+    };
+    match (&mut folded.kind, &original.kind) {
+        (ExprKind::Member { object, .. }, ExprKind::Member { object: from, .. }) => {
+            restore(object, from, false)
+        }
+        (ExprKind::Unary { operand, .. }, ExprKind::Unary { operand: from, .. }) => {
+            restore(operand, from, true)
+        }
+        (
+            ExprKind::Binary { op, left, right },
+            ExprKind::Binary {
+                left: l, right: r, ..
+            },
+        ) => {
+            let as_number = *op != BinaryOp::Add;
+            restore(left, l, as_number);
+            restore(right, r, as_number);
+        }
+        (
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            },
+            ExprKind::Conditional {
+                test: t,
+                consequent: c,
+                alternate: a,
+            },
+        ) => {
+            restore(test, t, true);
+            restore(consequent, c, false);
+            restore(alternate, a, false);
+        }
+        (
+            ExprKind::Call { arguments, .. },
+            ExprKind::Call {
+                arguments: from, ..
+            },
+        ) => {
+            arguments
+                .iter_mut()
+                .zip(from)
+                .for_each(|(argument, from)| restore(argument, from, false));
+        }
+        (
+            ExprKind::MethodCall {
+                receiver,
+                arguments,
+                ..
+            },
+            ExprKind::MethodCall {
+                receiver: r,
+                arguments: from,
+                ..
+            },
+        ) => {
+            restore(receiver, r, false);
+            arguments
+                .iter_mut()
+                .zip(from)
+                .for_each(|(argument, from)| restore(argument, from, false));
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,217 @@
+use crate::ast::Span;
+use crate::error::{ErrorKind, NodeError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// A numeric literal as written, which may not parse as a supported number, e.g. `0x1F`.
+    Number,
+    /// A quoted string literal, quotes included.
+    String,
+    /// A template literal, which is outside the supported subset.
+    Template,
+    /// An identifier or keyword.
+    Name,
+    /// An operator or punctuation, e.g. `&&` or `(`.
+    Punct(&'static str),
+    /// A character no token starts with, already reported.
+    Unknown,
+    Eof,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) span: Span,
+}
+
+/// Punctuators, longest first so that `===` isn't read as `==` followed by `=`.
+const PUNCTUATORS: &[&str] = &[
+    ">>>=", "...", "===", "!==", "**=", "<<=", ">>=", ">>>", "&&=", "||=", "??=", "=>", "==", "!=",
+    "<=", ">=", "&&", "||", "??", "?.", "**", "<<", ">>", "++", "--", "+=", "-=", "*=", "/=", "%=",
+    "&=", "|=", "^=", "(", ")", "[", "]", "{", "}", ",", ";", ":", "?", ".", "+", "-", "*", "/",
+    "%", "!", "~", "<", ">", "=", "&", "|", "^",
+];
+
+/// Splits `source` into tokens, ending with [`TokenKind::Eof`], and reports characters that
+/// can't start a token and unterminated literals or comments.
+pub(crate) fn tokenize(source: &str) -> (Vec<Token>, Vec<NodeError>) {
+    let mut lexer = Lexer {
+        source,
+        offset: 0,
+        errors: Vec::new(),
+    };
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next();
+        tokens.push(token);
+        if token.kind == TokenKind::Eof {
+            return (tokens, lexer.errors);
+        }
+    }
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    offset: usize,
+    errors: Vec<NodeError>,
+}
+
+impl Lexer<'_> {
+    fn rest(&self) -> &str {
+        &self.source[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.rest().chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn bump_while(&mut self, mut accept: impl FnMut(char) -> bool) {
+        while self.peek().is_some_and(&mut accept) {
+            self.bump();
+        }
+    }
+
+    fn error(&mut self, message: &str, start: usize) {
+        self.errors.push(NodeError::at(
+            ErrorKind::Parse {
+                message: message.to_string(),
+            },
+            Span::new(start, self.offset),
+        ));
+    }
+
+    fn next(&mut self) -> Token {
+        self.skip_trivia();
+        let start = self.offset;
+        let kind = match self.peek() {
+            None => TokenKind::Eof,
+            Some(c) if c.is_ascii_digit() => self.number(),
+            Some('.') if self.peek_second().is_some_and(|c| c.is_ascii_digit()) => self.number(),
+            Some(quote @ ('\'' | '"')) => self.string(quote),
+            Some('`') => self.template(),
+            Some(c) if is_name_start(c) => {
+                self.bump_while(is_name_part);
+                TokenKind::Name
+            }
+            Some(_) => self.punct(),
+        };
+        Token {
+            kind,
+            span: Span::new(start, self.offset),
+        }
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            let start = self.offset;
+            match (self.peek(), self.peek_second()) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.bump();
+                }
+                (Some('/'), Some('/')) => self.bump_while(|c| c != '\n'),
+                (Some('/'), Some('*')) => match self.rest()[2..].find("*/") {
+                    Some(end) => self.offset += end + 4,
+                    None => {
+                        self.offset = self.source.len();
+                        self.error("Unterminated comment", start);
+                    }
+                },
+                _ => return,
+            }
+        }
+    }
+
+    fn number(&mut self) -> TokenKind {
+        self.bump_while(|c| c.is_ascii_digit());
+        if self.peek() == Some('.') {
+            self.bump();
+            self.bump_while(|c| c.is_ascii_digit());
+        }
+        if matches!(self.peek(), Some('e' | 'E'))
+            && matches!(self.peek_second(), Some('0'..='9' | '+' | '-'))
+        {
+            self.bump();
+            self.bump();
+            self.bump_while(|c| c.is_ascii_digit());
+        }
+        // Hex, octal and binary literals, separators and suffixes such as `1n` stay part of
+        // the token so they are rejected as a whole
+        self.bump_while(is_name_part);
+        TokenKind::Number
+    }
+
+    fn string(&mut self, quote: char) -> TokenKind {
+        let start = self.offset;
+        self.bump();
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    self.bump();
+                }
+                Some(c) if c == quote => return TokenKind::String,
+                Some('\n') | None => break,
+                Some(_) => {}
+            }
+        }
+        self.error("Unterminated string literal", start);
+        TokenKind::String
+    }
+
+    fn template(&mut self) -> TokenKind {
+        let start = self.offset;
+        self.bump();
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    self.bump();
+                }
+                Some('`') => return TokenKind::Template,
+                None => break,
+                Some(_) => {}
+            }
+        }
+        self.error("Unterminated template literal", start);
+        TokenKind::Template
+    }
+
+    fn punct(&mut self) -> TokenKind {
+        let rest = self.rest();
+        // `a?.5:b` is a conditional, not an optional property read
+        let conditional =
+            rest.starts_with("?.") && rest[2..].starts_with(|c: char| c.is_ascii_digit());
+        let punct = PUNCTUATORS
+            .iter()
+            .filter(|punct| !(conditional && **punct == "?."))
+            .find(|punct| rest.starts_with(**punct));
+        match punct {
+            Some(punct) => {
+                self.offset += punct.len();
+                TokenKind::Punct(punct)
+            }
+            None => {
+                let start = self.offset;
+                let c = self.bump().unwrap_or_default();
+                self.error(&format!("Unexpected character `{}`", c), start);
+                TokenKind::Unknown
+            }
+        }
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_name_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
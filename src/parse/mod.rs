@@ -0,0 +1,80 @@
+//! Parsing of expression sources into the exprimo [`Expr`] tree.
+//!
+//! Expressions are parsed by a small precedence-climbing parser covering the JavaScript
+//! expression subset exprimo evaluates. The `rslint` feature keeps the full JavaScript
+//! parser the crate used before, to compare the two.
+
+use crate::ast::Expr;
+use crate::error::NodeError;
+
+mod lexer;
+mod parser;
+#[cfg(feature = "rslint")]
+mod rslint;
+
+pub(crate) use lexer::{tokenize, TokenKind};
+
+/// The statements of an expression source, lowered, with every problem found while parsing.
+pub(crate) struct Parsed {
+    pub(crate) statements: Vec<Expr>,
+    pub(crate) errors: Vec<NodeError>,
+}
+
+/// Parses `source` as a single expression into an exprimo [`Expr`].
+///
+/// Syntax errors, and input holding more than one statement, are reported with their
+/// position. Constructs outside the supported subset become [`ExprKind::Unsupported`]
+/// nodes, which only fail when they are evaluated.
+///
+/// [`ExprKind::Unsupported`]: crate::ast::ExprKind::Unsupported
+pub(crate) fn parse(source: &str) -> Result<Expr, NodeError> {
+    single(parse_all(source))
+}
+
+/// Parses `source`, collecting all syntax errors instead of stopping at the first one.
+///
+/// Parts of the tree the parser could not make sense of become
+/// [`ExprKind::Unsupported`](crate::ast::ExprKind::Unsupported) nodes with an empty span at
+/// the point the input broke off.
+pub(crate) fn parse_all(source: &str) -> Parsed {
+    parser::parse_all(source)
+}
+
+/// Parses `source` like [`parse`], with `rslint_parser`.
+#[cfg(feature = "rslint")]
+pub(crate) fn parse_rslint(source: &str) -> Result<Expr, NodeError> {
+    single(rslint::parse_all(source))
+}
+
+fn single(parsed: Parsed) -> Result<Expr, NodeError> {
+    let Parsed {
+        mut statements,
+        errors,
+    } = parsed;
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(statements.remove(0)),
+    }
+}
+
+/// Decodes the escapes of a string literal's contents. Other backslashes are kept as
+/// written, so regex patterns such as `'\d+'` don't need them doubled.
+pub(super) fn unescape(contents: &str) -> String {
+    let mut unescaped = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            ('\\', Some(&next @ ('\\' | '\'' | '"'))) => next,
+            ('\\', Some('n')) => '\n',
+            ('\\', Some('r')) => '\r',
+            ('\\', Some('t')) => '\t',
+            _ => {
+                unescaped.push(c);
+                continue;
+            }
+        };
+        chars.next();
+        unescaped.push(escaped);
+    }
+    unescaped
+}
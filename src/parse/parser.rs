@@ -0,0 +1,525 @@
+use serde_json::Value;
+
+use super::lexer::{tokenize, Token, TokenKind};
+use super::{unescape, Parsed};
+use crate::ast::{BinaryOp, Expr, ExprKind, Span, UnaryOp};
+use crate::error::{ErrorKind, NodeError};
+
+/// Parses the statements of `source` with exprimo's own precedence-climbing parser.
+pub(super) fn parse_all(source: &str) -> Parsed {
+    let (mut tokens, mut errors) = tokenize(source);
+    // Characters no token starts with were reported by the lexer already
+    tokens.retain(|token| token.kind != TokenKind::Unknown);
+
+    let mut parser = Parser {
+        source,
+        tokens,
+        position: 0,
+        errors: Vec::new(),
+    };
+    let statements = parser.statements();
+    errors.append(&mut parser.errors);
+    errors.sort_by_key(|error| error.span.map(|span| (span.start, span.end)));
+
+    match statements.get(1) {
+        _ if !errors.is_empty() => {}
+        Some(extra) => errors.push(NodeError::at(
+            ErrorKind::Parse {
+                message: "Expected a single expression, but found another statement".to_string(),
+            },
+            extra.span,
+        )),
+        None if statements.is_empty() => errors.push(
+            ErrorKind::Parse {
+                message: "Empty expression".to_string(),
+            }
+            .into(),
+        ),
+        None => {}
+    }
+
+    Parsed { statements, errors }
+}
+
+/// Operators binding two operands, with their binding power and whether they group to the
+/// right. Those without an exprimo operator parse into unsupported nodes.
+fn binary_operator(token: &str) -> Option<(u8, bool, Option<BinaryOp>)> {
+    let (power, op) = match token {
+        "??" => (1, None),
+        "||" => (2, Some(BinaryOp::Or)),
+        "&&" => (3, Some(BinaryOp::And)),
+        "|" => (4, None),
+        "^" => (5, None),
+        "&" => (6, None),
+        "==" => (7, Some(BinaryOp::Eq)),
+        "!=" => (7, Some(BinaryOp::NotEq)),
+        "===" => (7, Some(BinaryOp::StrictEq)),
+        "!==" => (7, Some(BinaryOp::StrictNotEq)),
+        "<" => (8, Some(BinaryOp::Lt)),
+        ">" => (8, Some(BinaryOp::Gt)),
+        "<=" => (8, Some(BinaryOp::LtEq)),
+        ">=" => (8, Some(BinaryOp::GtEq)),
+        "in" | "instanceof" => (8, None),
+        "<<" | ">>" | ">>>" => (9, None),
+        "+" => (10, Some(BinaryOp::Add)),
+        "-" => (10, Some(BinaryOp::Sub)),
+        "*" => (11, Some(BinaryOp::Mul)),
+        "/" => (11, Some(BinaryOp::Div)),
+        "%" => (11, Some(BinaryOp::Rem)),
+        "**" => return Some((12, true, None)),
+        _ => return None,
+    };
+    Some((power, false, op))
+}
+
+const ASSIGNMENT_OPERATORS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "**=", "<<=", ">>=", ">>>=", "&=", "|=", "^=", "&&=", "||=",
+    "??=",
+];
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+    errors: Vec<NodeError>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Token {
+        self.tokens[self.position]
+    }
+
+    fn peek_at(&self, position: usize) -> Token {
+        self.tokens[position.min(self.tokens.len() - 1)]
+    }
+
+    fn text(&self, token: Token) -> &'a str {
+        &self.source[token.span.start..token.span.end]
+    }
+
+    /// The text of the next token if it is an operator, punctuation or keyword.
+    fn peek_symbol(&self) -> &'a str {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::Punct(punct) => punct,
+            TokenKind::Name => self.text(token),
+            _ => "",
+        }
+    }
+
+    fn at(&self, punct: &str) -> bool {
+        matches!(self.peek().kind, TokenKind::Punct(found) if found == punct)
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.peek();
+        if token.kind != TokenKind::Eof {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let found = self.at(punct);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    /// End of the last token consumed.
+    fn end(&self) -> usize {
+        match self.position {
+            0 => 0,
+            position => self.tokens[position - 1].span.end,
+        }
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(start, self.end().max(start))
+    }
+
+    fn error(&mut self, message: String, span: Span) {
+        self.errors
+            .push(NodeError::at(ErrorKind::Parse { message }, span));
+    }
+
+    /// Reports that `expected` is missing where the next token is, without consuming it.
+    fn expected(&mut self, expected: &str) {
+        let token = self.peek();
+        let (found, span) = match token.kind {
+            TokenKind::Eof => ("none".to_string(), Span::new(self.end(), self.end())),
+            _ => (format!("`{}`", self.text(token)), token.span),
+        };
+        self.error(format!("Expected {}, but found {}", expected, found), span);
+    }
+
+    fn expect(&mut self, punct: &str) {
+        if !self.eat(punct) {
+            self.expected(&format!("`{}`", punct));
+        }
+    }
+
+    /// Stands in for a part of the expression found missing, with an empty span where it
+    /// should have been.
+    fn missing(&self, construct: &str) -> Expr {
+        let position = match self.peek().kind {
+            TokenKind::Eof => self.end(),
+            _ => self.peek().span.start,
+        };
+        Expr::new(
+            ExprKind::Unsupported(construct.to_string()),
+            Span::new(position, position),
+        )
+    }
+
+    fn unsupported(&self, construct: impl Into<String>, start: usize) -> Expr {
+        Expr::new(
+            ExprKind::Unsupported(construct.into()),
+            self.span_from(start),
+        )
+    }
+
+    fn can_start_expression(&self) -> bool {
+        match self.peek().kind {
+            TokenKind::Punct(punct) => {
+                matches!(punct, "(" | "[" | "{" | "!" | "-" | "+" | "~" | "++" | "--")
+            }
+            TokenKind::Eof | TokenKind::Unknown => false,
+            _ => true,
+        }
+    }
+
+    fn statements(&mut self) -> Vec<Expr> {
+        let mut statements = Vec::new();
+        loop {
+            while self.eat(";") {}
+            let token = self.peek();
+            if token.kind == TokenKind::Eof {
+                return statements;
+            }
+            if !self.can_start_expression() {
+                self.bump();
+                self.error(format!("Unexpected `{}`", self.text(token)), token.span);
+                continue;
+            }
+            statements.push(self.sequence());
+        }
+    }
+
+    /// `a, b`, which evaluates both but isn't supported.
+    fn sequence(&mut self) -> Expr {
+        let start = self.peek().span.start;
+        let first = self.assignment();
+        if !self.at(",") {
+            return first;
+        }
+        while self.eat(",") {
+            self.assignment();
+        }
+        self.unsupported("sequence expression", start)
+    }
+
+    fn assignment(&mut self) -> Expr {
+        let start = self.peek().span.start;
+        if self.at_arrow_function() {
+            match self.peek().kind {
+                TokenKind::Name => {
+                    self.bump();
+                }
+                _ => self.skip_balanced(),
+            }
+            self.bump();
+            if self.at("{") {
+                self.skip_balanced();
+            } else {
+                self.assignment();
+            }
+            return self.unsupported("arrow function", start);
+        }
+
+        let target = self.conditional();
+        if ASSIGNMENT_OPERATORS.contains(&self.peek_symbol()) {
+            self.bump();
+            self.assignment();
+            return self.unsupported("assignment", start);
+        }
+        target
+    }
+
+    fn at_arrow_function(&self) -> bool {
+        let after = match self.peek().kind {
+            TokenKind::Name => self.position + 1,
+            TokenKind::Punct("(") => self.matching_close(self.position) + 1,
+            _ => return false,
+        };
+        self.peek_at(after).kind == TokenKind::Punct("=>")
+    }
+
+    /// Position of the bracket closing the one at `position`, or of the end of input.
+    fn matching_close(&self, position: usize) -> usize {
+        let mut depth = 0usize;
+        for (offset, token) in self.tokens[position..].iter().enumerate() {
+            match token.kind {
+                TokenKind::Punct("(" | "[" | "{") => depth += 1,
+                TokenKind::Punct(")" | "]" | "}") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return position + offset;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.tokens.len() - 1
+    }
+
+    /// Consumes a bracketed construct outside the supported subset as a whole.
+    fn skip_balanced(&mut self) {
+        let open = self.peek();
+        let close = self.matching_close(self.position);
+        self.position = close;
+        if self.peek().kind == TokenKind::Eof {
+            let closing = match self.text(open) {
+                "(" => "`)`",
+                "[" => "`]`",
+                _ => "`}`",
+            };
+            self.expected(closing);
+        } else {
+            self.bump();
+        }
+    }
+
+    fn conditional(&mut self) -> Expr {
+        let start = self.peek().span.start;
+        let test = self.binary(1);
+        if !self.eat("?") {
+            return test;
+        }
+        let consequent = self.assignment();
+        self.expect(":");
+        let alternate = self.assignment();
+        Expr::new(
+            ExprKind::Conditional {
+                test: Box::new(test),
+                consequent: Box::new(consequent),
+                alternate: Box::new(alternate),
+            },
+            self.span_from(start),
+        )
+    }
+
+    /// Parses operators binding at least as tightly as `min_power`, grouping to the left
+    /// unless the operator says otherwise.
+    fn binary(&mut self, min_power: u8) -> Expr {
+        let start = self.peek().span.start;
+        let mut left = self.unary();
+        loop {
+            let symbol = self.peek_symbol();
+            let Some((power, right_assoc, op)) = binary_operator(symbol) else {
+                return left;
+            };
+            if power < min_power {
+                return left;
+            }
+            self.bump();
+            let right = self.binary(if right_assoc { power } else { power + 1 });
+            left = match op {
+                Some(op) => Expr::new(
+                    ExprKind::Binary {
+                        op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    },
+                    self.span_from(start),
+                ),
+                None => self.unsupported(format!("binary operator '{}'", symbol), start),
+            };
+        }
+    }
+
+    fn unary(&mut self) -> Expr {
+        let start = self.peek().span.start;
+        let symbol = self.peek_symbol();
+        let op = match symbol {
+            "!" => Some(UnaryOp::Not),
+            "-" => Some(UnaryOp::Minus),
+            "+" => Some(UnaryOp::Plus),
+            "~" | "typeof" | "void" | "delete" | "await" => None,
+            "++" | "--" => {
+                self.bump();
+                self.unary();
+                return self.unsupported("update expression", start);
+            }
+            _ => return self.postfix(),
+        };
+        self.bump();
+        let operand = self.unary();
+        match op {
+            Some(op) => Expr::new(
+                ExprKind::Unary {
+                    op,
+                    operand: Box::new(operand),
+                },
+                self.span_from(start),
+            ),
+            None => self.unsupported(format!("unary operator '{}'", symbol), start),
+        }
+    }
+
+    fn postfix(&mut self) -> Expr {
+        let start = self.peek().span.start;
+        let mut expr = self.primary();
+        loop {
+            let symbol = self.peek_symbol();
+            expr = match symbol {
+                "." | "?." => {
+                    let optional = symbol == "?.";
+                    self.bump();
+                    match self.peek().kind {
+                        TokenKind::Name => {
+                            let name = self.bump();
+                            let property = self.text(name).to_string();
+                            Expr::new(
+                                ExprKind::Member {
+                                    object: Box::new(expr),
+                                    property,
+                                    optional,
+                                },
+                                self.span_from(start),
+                            )
+                        }
+                        TokenKind::Punct("(") if optional => {
+                            self.arguments();
+                            self.unsupported("optional call", start)
+                        }
+                        TokenKind::Punct("[") if optional => {
+                            self.skip_balanced();
+                            self.unsupported("computed member access", start)
+                        }
+                        _ => {
+                            self.expected("a property name");
+                            return self.missing("member expression without a property");
+                        }
+                    }
+                }
+                "(" => {
+                    let arguments = self.arguments();
+                    let kind = match expr.kind {
+                        ExprKind::Identifier(function) => ExprKind::Call {
+                            function,
+                            arguments,
+                        },
+                        ExprKind::Member {
+                            object, property, ..
+                        } => ExprKind::MethodCall {
+                            receiver: object,
+                            method: property,
+                            arguments,
+                        },
+                        _ => ExprKind::Unsupported("callee in call expression".to_string()),
+                    };
+                    Expr::new(kind, self.span_from(start))
+                }
+                "[" => {
+                    self.skip_balanced();
+                    self.unsupported("computed member access", start)
+                }
+                "++" | "--" => {
+                    self.bump();
+                    self.unsupported("update expression", start)
+                }
+                _ if self.peek().kind == TokenKind::Template => {
+                    self.bump();
+                    self.unsupported("tagged template", start)
+                }
+                _ => return expr,
+            };
+        }
+    }
+
+    fn arguments(&mut self) -> Vec<Expr> {
+        self.expect("(");
+        let mut arguments = Vec::new();
+        while !self.eat(")") {
+            let start = self.peek().span.start;
+            let argument = if self.eat("...") {
+                self.assignment();
+                self.unsupported("spread argument", start)
+            } else {
+                self.assignment()
+            };
+            arguments.push(argument);
+            if !self.eat(",") {
+                self.expect(")");
+                break;
+            }
+        }
+        arguments
+    }
+
+    fn primary(&mut self) -> Expr {
+        let token = self.peek();
+        let start = token.span.start;
+        let text = self.text(token);
+        let kind = match token.kind {
+            TokenKind::Number => match text
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+            {
+                Some(number) => ExprKind::Literal(Value::Number(number)),
+                None => ExprKind::Unsupported(format!("literal {}", text)),
+            },
+            TokenKind::String => {
+                let quote = &text[..1];
+                let contents = &text[1..];
+                let contents = match contents.strip_suffix(quote) {
+                    Some(contents) if text.len() > 1 => contents,
+                    // Unterminated, which the lexer reported already
+                    _ => contents,
+                };
+                ExprKind::Literal(Value::String(unescape(contents)))
+            }
+            TokenKind::Template => ExprKind::Unsupported("template literal".to_string()),
+            TokenKind::Name => match text {
+                "true" => ExprKind::Literal(Value::Bool(true)),
+                "false" => ExprKind::Literal(Value::Bool(false)),
+                "null" => ExprKind::Literal(Value::Null),
+                "this" => ExprKind::Unsupported("this".to_string()),
+                "new" => {
+                    self.bump();
+                    self.postfix();
+                    return self.unsupported("new expression", start);
+                }
+                _ => ExprKind::Identifier(text.to_string()),
+            },
+            TokenKind::Punct("(") => {
+                self.bump();
+                if self.at(")") {
+                    self.expected("an expression");
+                    self.bump();
+                    return self.missing("empty expression");
+                }
+                let expr = self.sequence();
+                self.expect(")");
+                return expr;
+            }
+            TokenKind::Punct("[") => {
+                self.skip_balanced();
+                return self.unsupported("array literal", start);
+            }
+            TokenKind::Punct("{") => {
+                self.skip_balanced();
+                return self.unsupported("object literal", start);
+            }
+            _ => {
+                self.expected("an expression");
+                return self.missing("missing expression");
+            }
+        };
+        self.bump();
+        Expr::new(kind, token.span)
+    }
+}
@@ -0,0 +1,286 @@
+use rslint_parser::{
+    ast::{BinExpr, BinOp, CallExpr, CondExpr, DotExpr, Expr as SyntaxExpr, UnaryExpr, UnaryOp},
+    parse_text, AstNode, SyntaxKind, SyntaxNode, SyntaxNodeExt,
+};
+use serde_json::Value;
+
+use super::{unescape, Parsed};
+use crate::ast::{BinaryOp, Expr, ExprKind, Span, UnaryOp as AstUnaryOp};
+use crate::error::{ErrorKind, NodeError};
+
+/// Parses `source` with `rslint_parser`, a full JavaScript parser, and lowers the syntax
+/// tree into exprimo's.
+pub(super) fn parse_all(source: &str) -> Parsed {
+    let parsed = parse_text(source, 0);
+    let mut errors: Vec<NodeError> = parsed
+        .errors()
+        .iter()
+        .map(|error| {
+            let kind = ErrorKind::Parse {
+                message: error.title.clone(),
+            };
+            match &error.primary {
+                Some(primary) => NodeError::at(
+                    kind,
+                    Span::new(primary.span.range.start, primary.span.range.end),
+                ),
+                None => kind.into(),
+            }
+        })
+        .collect();
+
+    let nodes: Vec<SyntaxNode> = parsed.syntax().children().collect();
+    match nodes.get(1) {
+        _ if !errors.is_empty() => {}
+        Some(extra) => errors.push(NodeError::at(
+            ErrorKind::Parse {
+                message: "Expected a single expression, but found another statement".to_string(),
+            },
+            span(extra),
+        )),
+        None if nodes.is_empty() => errors.push(
+            ErrorKind::Parse {
+                message: "Empty expression".to_string(),
+            }
+            .into(),
+        ),
+        None => {}
+    }
+
+    Parsed {
+        statements: nodes.iter().map(lower).collect(),
+        errors,
+    }
+}
+
+fn span(node: &SyntaxNode) -> Span {
+    let range = node.trimmed_range();
+    Span::new(range.start().into(), range.end().into())
+}
+
+fn unsupported(node: &SyntaxNode, construct: impl Into<String>) -> Expr {
+    Expr::new(ExprKind::Unsupported(construct.into()), span(node))
+}
+
+/// Stands in for a part of `node` the parser found missing.
+fn missing(node: &SyntaxNode, construct: &str) -> Expr {
+    let end = node.trimmed_range().end().into();
+    Expr::new(
+        ExprKind::Unsupported(construct.to_string()),
+        Span::new(end, end),
+    )
+}
+
+fn lower_child(parent: &SyntaxNode, child: Option<SyntaxExpr>, construct: &str) -> Expr {
+    match child {
+        Some(child) => lower(child.syntax()),
+        None => missing(parent, construct),
+    }
+}
+
+fn lower(node: &SyntaxNode) -> Expr {
+    match node.kind() {
+        SyntaxKind::EXPR_STMT | SyntaxKind::GROUPING_EXPR => match node.first_child() {
+            Some(expr) => lower(&expr),
+            None => missing(node, "empty expression"),
+        },
+        SyntaxKind::NAME_REF | SyntaxKind::NAME => Expr::new(
+            ExprKind::Identifier(node.trimmed_text().to_string()),
+            span(node),
+        ),
+        SyntaxKind::LITERAL => lower_literal(node),
+        SyntaxKind::DOT_EXPR => lower_dot_expr(&DotExpr::cast(node.clone()).unwrap()),
+        SyntaxKind::BIN_EXPR => lower_bin_expr(&BinExpr::cast(node.clone()).unwrap()),
+        SyntaxKind::UNARY_EXPR => lower_unary_expr(&UnaryExpr::cast(node.clone()).unwrap()),
+        SyntaxKind::COND_EXPR => lower_cond_expr(&CondExpr::cast(node.clone()).unwrap()),
+        SyntaxKind::CALL_EXPR => lower_call_expr(&CallExpr::cast(node.clone()).unwrap()),
+        SyntaxKind::ERROR => missing(node, "invalid syntax"),
+        kind => unsupported(node, format!("syntax kind {:?}", kind)),
+    }
+}
+
+fn lower_literal(node: &SyntaxNode) -> Expr {
+    let literal_str = node.trimmed_text().to_string();
+
+    // Handle numeric literals
+    if let Ok(number) = literal_str.parse::<f64>() {
+        return match serde_json::Number::from_f64(number) {
+            Some(number) => Expr::new(ExprKind::Literal(Value::Number(number)), span(node)),
+            None => unsupported(node, format!("literal {}", literal_str)),
+        };
+    }
+
+    // Handle string literals
+    let value = if literal_str.starts_with('"') || literal_str.starts_with('\'') {
+        Value::String(unescape(&literal_str[1..literal_str.len() - 1]))
+    } else {
+        match literal_str.as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "null" => Value::Null,
+            _ => return unsupported(node, format!("literal {}", literal_str)),
+        }
+    };
+
+    Expr::new(ExprKind::Literal(value), span(node))
+}
+
+fn lower_dot_expr(dot_expr: &DotExpr) -> Expr {
+    let node = dot_expr.syntax();
+    let property = match dot_expr.prop() {
+        Some(prop) => prop.syntax().trimmed_text().to_string(),
+        None => return missing(node, "member expression without a property"),
+    };
+    let object = lower_child(
+        node,
+        dot_expr.object(),
+        "member expression without an object",
+    );
+
+    Expr::new(
+        ExprKind::Member {
+            object: Box::new(object),
+            property,
+            optional: dot_expr.opt_chain_token().is_some(),
+        },
+        span(node),
+    )
+}
+
+fn lower_bin_expr(bin_expr: &BinExpr) -> Expr {
+    let node = bin_expr.syntax();
+    let left = lower_child(
+        node,
+        bin_expr.lhs(),
+        "binary expression without a left operand",
+    );
+    let right = lower_child(
+        node,
+        bin_expr.rhs(),
+        "binary expression without a right operand",
+    );
+
+    let op = match bin_expr.op_details() {
+        Some((_, BinOp::Plus)) => BinaryOp::Add,
+        Some((_, BinOp::Minus)) => BinaryOp::Sub,
+        Some((_, BinOp::Times)) => BinaryOp::Mul,
+        Some((_, BinOp::Divide)) => BinaryOp::Div,
+        Some((_, BinOp::Remainder)) => BinaryOp::Rem,
+        Some((_, BinOp::LogicalAnd)) => BinaryOp::And,
+        Some((_, BinOp::LogicalOr)) => BinaryOp::Or,
+        Some((_, BinOp::Equality)) => BinaryOp::Eq,
+        Some((_, BinOp::StrictEquality)) => BinaryOp::StrictEq,
+        Some((_, BinOp::Inequality)) => BinaryOp::NotEq,
+        Some((_, BinOp::StrictInequality)) => BinaryOp::StrictNotEq,
+        Some((_, BinOp::GreaterThan)) => BinaryOp::Gt,
+        Some((_, BinOp::LessThan)) => BinaryOp::Lt,
+        Some((_, BinOp::GreaterThanOrEqual)) => BinaryOp::GtEq,
+        Some((_, BinOp::LessThanOrEqual)) => BinaryOp::LtEq,
+        _ => {
+            let op = bin_expr.op_token().map(|t| t.text().to_string());
+            return unsupported(
+                node,
+                format!("binary operator '{}'", op.unwrap_or_default()),
+            );
+        }
+    };
+
+    Expr::new(
+        ExprKind::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        },
+        span(node),
+    )
+}
+
+fn lower_unary_expr(unary_expr: &UnaryExpr) -> Expr {
+    let node = unary_expr.syntax();
+    let operand = lower_child(
+        node,
+        unary_expr.expr(),
+        "unary expression without an operand",
+    );
+
+    let op = match unary_expr.op_details() {
+        Some((_, UnaryOp::LogicalNot)) => AstUnaryOp::Not,
+        Some((_, UnaryOp::Minus)) => AstUnaryOp::Minus,
+        Some((_, UnaryOp::Plus)) => AstUnaryOp::Plus,
+        _ => {
+            let op = unary_expr.op_token().map(|t| t.text().to_string());
+            return unsupported(node, format!("unary operator '{}'", op.unwrap_or_default()));
+        }
+    };
+
+    Expr::new(
+        ExprKind::Unary {
+            op,
+            operand: Box::new(operand),
+        },
+        span(node),
+    )
+}
+
+fn lower_cond_expr(cond_expr: &CondExpr) -> Expr {
+    let node = cond_expr.syntax();
+    let test = lower_child(
+        node,
+        cond_expr.test(),
+        "conditional expression without a test",
+    );
+    let consequent = lower_child(
+        node,
+        cond_expr.cons(),
+        "conditional expression without a consequent",
+    );
+    let alternate = lower_child(
+        node,
+        cond_expr.alt(),
+        "conditional expression without an alternate",
+    );
+
+    Expr::new(
+        ExprKind::Conditional {
+            test: Box::new(test),
+            consequent: Box::new(consequent),
+            alternate: Box::new(alternate),
+        },
+        span(node),
+    )
+}
+
+fn lower_call_expr(call_expr: &CallExpr) -> Expr {
+    let node = call_expr.syntax();
+    let callee = match call_expr.callee() {
+        Some(callee) => callee,
+        None => return missing(node, "call expression without a callee"),
+    };
+    let arguments = match call_expr.arguments() {
+        Some(args) => args.args().map(|arg| lower(arg.syntax())).collect(),
+        None => vec![],
+    };
+
+    let kind = match callee.syntax().kind() {
+        SyntaxKind::DOT_EXPR => {
+            let dot_expr = DotExpr::cast(callee.syntax().clone()).unwrap();
+            let method = match dot_expr.prop() {
+                Some(prop) => prop.syntax().trimmed_text().to_string(),
+                None => return missing(node, "method call without a method name"),
+            };
+            let receiver = lower_child(node, dot_expr.object(), "method call without a receiver");
+            ExprKind::MethodCall {
+                receiver: Box::new(receiver),
+                method,
+                arguments,
+            }
+        }
+        SyntaxKind::NAME_REF => ExprKind::Call {
+            function: callee.syntax().trimmed_text().to_string(),
+            arguments,
+        },
+        _ => return unsupported(node, "callee in call expression"),
+    };
+
+    Expr::new(kind, span(node))
+}
@@ -0,0 +1,71 @@
+use serde_json::Value;
+
+use crate::ast::ExprKind;
+use crate::expression::Expression;
+use crate::optimize::Folding;
+use crate::scope::Scope;
+use crate::Evaluator;
+
+/// The outcome of [`Evaluator::partial_evaluate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Partial {
+    /// The known variables were enough to evaluate the expression.
+    Value(Value),
+    /// What is left to evaluate once the remaining variables are given.
+    Residual(Expression),
+    /// The expression evaluates to `value` unless evaluating `residual` fails. Only
+    /// operands that can't change the value are left to evaluate, such as the `x` in
+    /// `false && x`, but they may still fail.
+    ValueUnlessFails { value: Value, residual: Expression },
+}
+
+impl Partial {
+    /// The final value, if the expression no longer depends on unknown variables.
+    pub fn value(&self) -> Option<&Value> {
+        match self {
+            Partial::Value(value) => Some(value),
+            Partial::Residual(_) | Partial::ValueUnlessFails { .. } => None,
+        }
+    }
+}
+
+impl Evaluator {
+    /// Evaluates as much of `expression` as the `known` variables allow, with the same
+    /// semantics as a full evaluation.
+    ///
+    /// Variables `known` doesn't define are left in the residual expression, even if this
+    /// evaluator's context defines them, since the variables given later may override them.
+    /// Parts that fail to evaluate are left in place too, so the error surfaces when the
+    /// residual is evaluated. Known arrays and objects have no literal form, and neither
+    /// have integers where `+` or a function would tell them from floats, so a residual
+    /// still using one reads it from its variable: evaluate it with the known variables as
+    /// well as the rest.
+    ///
+    /// When `false && x` or `true || x` decide the value but `x` may fail, the result is
+    /// [`Partial::ValueUnlessFails`]: a rule that is `false` unless it fails can be skipped
+    /// by callers that don't need its errors.
+    pub fn partial_evaluate<S: Scope + ?Sized>(
+        &self,
+        expression: &Expression,
+        known: &S,
+    ) -> Partial {
+        let mut folding = Folding {
+            source: expression.source(),
+            known: &known,
+            drop_fallible: false,
+        };
+        let root = self.fold(expression.root(), &folding);
+        if let ExprKind::Literal(value) = root.kind {
+            return Partial::Value(value);
+        }
+
+        // Folding the residual again, dropping the operands that can only fail
+        folding.drop_fallible = true;
+        let decided = self.fold(&root, &folding);
+        let residual = Expression::new(expression.source(), root);
+        match decided.kind {
+            ExprKind::Literal(value) => Partial::ValueUnlessFails { value, residual },
+            _ => Partial::Residual(residual),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::ast::{Expr, ExprKind};
+use crate::error::{ErrorKind, NodeError};
+use crate::limits::Limit;
+
+/// Built-in methods whose first argument is a regular expression pattern.
+pub(crate) const PATTERN_METHODS: &[&str] = &["regexReplace"];
+
+/// How regular expressions supplied by expressions are compiled.
+///
+/// Patterns come from the expression, so a hostile one could take a lot of time and memory
+/// to compile. Compilation fails with [`ErrorKind::LimitExceeded`] once a pattern would
+/// outgrow the size limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegexPolicy {
+    /// Most bytes a compiled pattern may take, see [`RegexBuilder::size_limit`].
+    pub size_limit: usize,
+    /// Most bytes the lazy DFA may cache while matching one pattern, see
+    /// [`RegexBuilder::dfa_size_limit`].
+    pub dfa_size_limit: usize,
+    /// How many compiled patterns are kept for reuse across evaluations. The least recently
+    /// used pattern is dropped first; zero disables the cache.
+    pub cache_capacity: usize,
+    /// Whether patterns may be computed at evaluation time, e.g. read from a variable. When
+    /// false, only string literals written in the expression are accepted.
+    pub allow_dynamic: bool,
+}
+
+impl Default for RegexPolicy {
+    fn default() -> Self {
+        RegexPolicy {
+            size_limit: 1 << 20,
+            dfa_size_limit: 1 << 20,
+            cache_capacity: 64,
+            allow_dynamic: true,
+        }
+    }
+}
+
+impl RegexPolicy {
+    /// Rejects a call to a pattern method whose pattern isn't a literal, if the policy
+    /// requires literal patterns.
+    pub(crate) fn check_call(&self, method: &str, arguments: &[Expr]) -> Result<(), NodeError> {
+        if self.allow_dynamic || !PATTERN_METHODS.contains(&method) {
+            return Ok(());
+        }
+        match arguments.first() {
+            Some(pattern) if !matches!(pattern.kind, ExprKind::Literal(_)) => {
+                let kind = ErrorKind::DynamicPattern {
+                    method: method.to_string(),
+                };
+                Err(NodeError::at(kind, pattern.span))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Compiled patterns shared by every evaluation of an evaluator.
+pub(crate) struct RegexCache {
+    policy: RegexPolicy,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    // Each pattern with the tick it was last used at
+    patterns: HashMap<String, (Arc<Regex>, u64)>,
+    tick: u64,
+}
+
+impl RegexCache {
+    pub(crate) fn new(policy: RegexPolicy) -> Self {
+        RegexCache {
+            policy,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    pub(crate) fn policy(&self) -> &RegexPolicy {
+        &self.policy
+    }
+
+    /// Returns the compiled `pattern` passed to `method`, compiling it within the policy's
+    /// limits on first use.
+    pub(crate) fn get(&self, method: &str, pattern: &str) -> Result<Arc<Regex>, ErrorKind> {
+        if let Some(regex) = self.lookup(pattern) {
+            return Ok(regex);
+        }
+
+        let regex = RegexBuilder::new(pattern)
+            .size_limit(self.policy.size_limit)
+            .dfa_size_limit(self.policy.dfa_size_limit)
+            .build()
+            .map(Arc::new)
+            .map_err(|e| match e {
+                regex::Error::CompiledTooBig(_) => ErrorKind::LimitExceeded {
+                    limit: Limit::RegexSize,
+                    max: self.policy.size_limit,
+                },
+                e => ErrorKind::InvalidArguments {
+                    method: method.to_string(),
+                    message: e.to_string(),
+                },
+            })?;
+
+        self.insert(pattern, regex.clone());
+        Ok(regex)
+    }
+
+    fn lookup(&self, pattern: &str) -> Option<Arc<Regex>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.tick += 1;
+        let tick = entries.tick;
+        let (regex, used) = entries.patterns.get_mut(pattern)?;
+        *used = tick;
+        Some(regex.clone())
+    }
+
+    fn insert(&self, pattern: &str, regex: Arc<Regex>) {
+        if self.policy.cache_capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.patterns.len() >= self.policy.cache_capacity {
+            // Capacities are small, so finding the least recently used entry by scanning is
+            // cheaper than keeping a separate order
+            let oldest = entries
+                .patterns
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(pattern, _)| pattern.clone());
+            if let Some(oldest) = oldest {
+                entries.patterns.remove(&oldest);
+            }
+        }
+        let tick = entries.tick;
+        entries.patterns.insert(pattern.to_string(), (regex, tick));
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::error::ErrorKind;
+
+/// What expressions may use: which host functions they may call, which built-in methods
+/// and which top-level variables they may read.
+///
+/// Each kind of [`Capability`] has an optional allow list and a deny list. Everything is
+/// allowed until an allow list is given, after which only the names on it are; a denied
+/// name is forbidden either way. Breaking the policy fails with [`ErrorKind::Forbidden`],
+/// both from [`Evaluator::validate`](crate::Evaluator::validate) and when evaluating.
+///
+/// ```
+/// use exprimo::AccessPolicy;
+///
+/// let policy = AccessPolicy::new()
+///     .allow_functions(["lookup"])
+///     .deny_methods(["regexReplace"])
+///     .allow_globals(["user", "event"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessPolicy {
+    functions: Rule,
+    methods: Rule,
+    globals: Rule,
+}
+
+/// Something an expression can use, as governed by an [`AccessPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// A registered host function, e.g. `lookup` in `lookup(id)`.
+    Function,
+    /// A built-in method, e.g. `toUpperCase` in `name.toUpperCase()`.
+    Method,
+    /// A top-level variable, from the evaluation's variables or the evaluator's context.
+    Global,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::Function => "function",
+            Capability::Method => "method",
+            Capability::Global => "global",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Rule {
+    allowed: Option<HashSet<String>>,
+    denied: HashSet<String>,
+}
+
+impl Rule {
+    fn allows(&self, name: &str) -> bool {
+        !self.denied.contains(name)
+            && match &self.allowed {
+                Some(allowed) => allowed.contains(name),
+                None => true,
+            }
+    }
+
+    fn allow<S: Into<String>>(&mut self, names: impl IntoIterator<Item = S>) {
+        self.allowed
+            .get_or_insert_with(HashSet::new)
+            .extend(names.into_iter().map(Into::into));
+    }
+
+    fn deny<S: Into<String>>(&mut self, names: impl IntoIterator<Item = S>) {
+        self.denied.extend(names.into_iter().map(Into::into));
+    }
+}
+
+impl AccessPolicy {
+    /// A policy allowing everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows calling only these host functions, and any allowed before.
+    pub fn allow_functions<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.functions.allow(names);
+        self
+    }
+
+    pub fn deny_functions<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.functions.deny(names);
+        self
+    }
+
+    /// Allows calling only these built-in methods, on any receiver, and any allowed before.
+    pub fn allow_methods<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.methods.allow(names);
+        self
+    }
+
+    pub fn deny_methods<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.methods.deny(names);
+        self
+    }
+
+    /// Allows reading only these top-level variables, and any allowed before.
+    pub fn allow_globals<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.globals.allow(names);
+        self
+    }
+
+    pub fn deny_globals<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.globals.deny(names);
+        self
+    }
+
+    pub fn allows(&self, capability: Capability, name: &str) -> bool {
+        match capability {
+            Capability::Function => self.functions.allows(name),
+            Capability::Method => self.methods.allows(name),
+            Capability::Global => self.globals.allows(name),
+        }
+    }
+
+    pub(crate) fn check(&self, capability: Capability, name: &str) -> Result<(), ErrorKind> {
+        if self.allows(capability, name) {
+            Ok(())
+        } else {
+            Err(ErrorKind::Forbidden {
+                capability,
+                name: name.to_string(),
+            })
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use serde_json::Value;
+
+use crate::ast::{BinaryOp, Expr, ExprKind, UnaryOp};
+use crate::error::EvaluationError;
+use crate::Evaluator;
+
+/// How [`Expression::format`](crate::Expression::format) lays out an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Quote used for string literals, unless the other needs fewer escapes.
+    pub quote: Quote,
+    /// Width past which operator chains, conditionals and argument lists are broken over
+    /// several lines. `None` keeps everything on one line.
+    pub max_width: Option<usize>,
+    /// Spaces added per level of indentation on continuation lines.
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            quote: Quote::Single,
+            max_width: None,
+            indent: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quote {
+    #[default]
+    Single,
+    Double,
+}
+
+impl Quote {
+    fn char(self) -> char {
+        match self {
+            Quote::Single => '\'',
+            Quote::Double => '"',
+        }
+    }
+
+    fn other(self) -> Quote {
+        match self {
+            Quote::Single => Quote::Double,
+            Quote::Double => Quote::Single,
+        }
+    }
+}
+
+impl Evaluator {
+    /// Normalises the source of an expression, see [`Expression::format`](crate::Expression::format).
+    pub fn format(
+        &self,
+        expression: &str,
+        options: &FormatOptions,
+    ) -> Result<String, EvaluationError> {
+        Ok(self.compile(expression)?.format(options))
+    }
+}
+
+/// Prints `expr` back to source, adding parentheses only where precedence requires them.
+/// Unsupported constructs are copied from `source`, which the node spans index into.
+pub(crate) fn print(expr: &Expr, source: &str, options: &FormatOptions) -> String {
+    let printer = Printer { source, options };
+    let mut out = String::new();
+    printer.write(expr, 0, 0, &mut out);
+    out
+}
+
+const CONDITIONAL: u8 = 1;
+const UNARY: u8 = 8;
+const POSTFIX: u8 = 9;
+const PRIMARY: u8 = 10;
+
+fn binary_precedence(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Or => 2,
+        BinaryOp::And => 3,
+        BinaryOp::Eq | BinaryOp::NotEq | BinaryOp::StrictEq | BinaryOp::StrictNotEq => 4,
+        BinaryOp::Gt | BinaryOp::Lt | BinaryOp::GtEq | BinaryOp::LtEq => 5,
+        BinaryOp::Add | BinaryOp::Sub => 6,
+        BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 7,
+    }
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.kind {
+        ExprKind::Literal(Value::Number(n)) if n.as_f64().is_some_and(f64::is_sign_negative) => {
+            UNARY
+        }
+        ExprKind::Literal(_) | ExprKind::Identifier(_) => PRIMARY,
+        ExprKind::Member { .. } | ExprKind::Call { .. } | ExprKind::MethodCall { .. } => POSTFIX,
+        ExprKind::Unary { .. } => UNARY,
+        ExprKind::Binary { op, .. } => binary_precedence(*op),
+        ExprKind::Conditional { .. } => CONDITIONAL,
+        // Copied verbatim, so only safe to nest in parentheses
+        ExprKind::Unsupported(_) => 0,
+    }
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    options: &'a FormatOptions,
+}
+
+impl Printer<'_> {
+    /// Prints `expr` on a single line.
+    fn flat(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal(value) => self.literal(value),
+            ExprKind::Identifier(name) => name.clone(),
+            ExprKind::Member {
+                object,
+                property,
+                optional,
+            } => {
+                let dot = if *optional { "?." } else { "." };
+                format!("{}{}{}", self.receiver(object), dot, property)
+            }
+            ExprKind::Unary { op, operand } => {
+                let operand = self.operand(operand, UNARY);
+                // `- -a` must not become the decrement `--a`
+                if *op != UnaryOp::Not && operand.starts_with(['-', '+']) {
+                    format!("{}({})", op.as_str(), operand)
+                } else {
+                    format!("{}{}", op.as_str(), operand)
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let precedence = binary_precedence(*op);
+                format!(
+                    "{} {} {}",
+                    self.operand(left, precedence),
+                    op.as_str(),
+                    self.operand(right, precedence + 1)
+                )
+            }
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => format!(
+                "{} ? {} : {}",
+                self.operand(test, CONDITIONAL + 1),
+                self.operand(consequent, CONDITIONAL),
+                self.operand(alternate, CONDITIONAL)
+            ),
+            ExprKind::Call {
+                function,
+                arguments,
+            } => format!("{}({})", function, self.arguments(arguments)),
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => format!(
+                "{}.{}({})",
+                self.receiver(receiver),
+                method,
+                self.arguments(arguments)
+            ),
+            ExprKind::Unsupported(_) => self
+                .source
+                .get(expr.span.start..expr.span.end)
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    /// Prints `expr` as an operand of an operator binding at least as tightly as `min`.
+    fn operand(&self, expr: &Expr, min: u8) -> String {
+        let printed = self.flat(expr);
+        if precedence(expr) < min {
+            format!("({})", printed)
+        } else {
+            printed
+        }
+    }
+
+    /// Prints the object of a property read or method call, where a number such as `1`
+    /// would otherwise read as `1.`.
+    fn receiver(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal(Value::Number(_)) => format!("({})", self.flat(expr)),
+            _ => self.operand(expr, POSTFIX),
+        }
+    }
+
+    fn arguments(&self, arguments: &[Expr]) -> String {
+        arguments
+            .iter()
+            .map(|arg| self.operand(arg, CONDITIONAL))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn literal(&self, value: &Value) -> String {
+        // Folding keeps arrays and objects behind their variables and decoding rejects them,
+        // as the parser has no literal for either
+        debug_assert!(
+            !value.is_array() && !value.is_object(),
+            "no source form for {value}"
+        );
+        match value {
+            Value::Number(n) => match n.as_f64() {
+                // Whole numbers are floats in expressions, but read better without the `.0`
+                Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() < 1e15 => {
+                    format!("{:.0}", f)
+                }
+                _ => n.to_string(),
+            },
+            Value::String(s) => {
+                let preferred = self.options.quote;
+                let quote = if s.contains(preferred.char()) && !s.contains(preferred.other().char())
+                {
+                    preferred.other()
+                } else {
+                    preferred
+                };
+                quoted(s, quote.char())
+            }
+            value => value.to_string(),
+        }
+    }
+
+    /// Appends `expr` to `out`, breaking it over lines indented by `indent` levels when it
+    /// doesn't fit within the maximum width.
+    fn write(&self, expr: &Expr, min: u8, indent: usize, out: &mut String) {
+        let flat = self.operand(expr, min);
+        let line_start = out.rfind('\n').map_or(0, |newline| newline + 1);
+        let column = out[line_start..].chars().count();
+        let fits = match self.options.max_width {
+            Some(width) => column + flat.chars().count() <= width,
+            None => true,
+        };
+        if fits {
+            out.push_str(&flat);
+            return;
+        }
+
+        if precedence(expr) < min {
+            out.push('(');
+            self.newline(indent + 1, out);
+            self.write(expr, 0, indent + 1, out);
+            self.newline(indent, out);
+            out.push(')');
+            return;
+        }
+
+        match &expr.kind {
+            ExprKind::Binary { op, .. } => {
+                // Chains of the same precedence break before each operator
+                let precedence = binary_precedence(*op);
+                let mut operands = Vec::new();
+                let mut first = expr;
+                while let ExprKind::Binary { op, left, right } = &first.kind {
+                    if binary_precedence(*op) != precedence {
+                        break;
+                    }
+                    operands.push((*op, &**right));
+                    first = left;
+                }
+                self.write(first, precedence, indent, out);
+                for (op, operand) in operands.into_iter().rev() {
+                    self.newline(indent + 1, out);
+                    out.push_str(op.as_str());
+                    out.push(' ');
+                    self.write(operand, precedence + 1, indent + 1, out);
+                }
+            }
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => {
+                self.write(test, CONDITIONAL + 1, indent, out);
+                self.newline(indent + 1, out);
+                out.push_str("? ");
+                self.write(consequent, CONDITIONAL, indent + 1, out);
+                self.newline(indent + 1, out);
+                out.push_str(": ");
+                self.write(alternate, CONDITIONAL, indent + 1, out);
+            }
+            ExprKind::Call {
+                function,
+                arguments,
+            } if !arguments.is_empty() => {
+                out.push_str(function);
+                self.write_arguments(arguments, indent, out);
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } if !arguments.is_empty() => {
+                out.push_str(&self.receiver(receiver));
+                out.push('.');
+                out.push_str(method);
+                self.write_arguments(arguments, indent, out);
+            }
+            _ => out.push_str(&flat),
+        }
+    }
+
+    fn write_arguments(&self, arguments: &[Expr], indent: usize, out: &mut String) {
+        out.push('(');
+        for argument in arguments {
+            self.newline(indent + 1, out);
+            self.write(argument, CONDITIONAL, indent + 1, out);
+            out.push(',');
+        }
+        self.newline(indent, out);
+        out.push(')');
+    }
+
+    fn newline(&self, indent: usize, out: &mut String) {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent * self.options.indent));
+    }
+}
+
+/// Quotes `s`, escaping what the parser would otherwise read differently.
+fn quoted(s: &str, quote: char) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push(quote);
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // A lone backslash reads as itself unless it starts an escape
+            '\\' if matches!(
+                chars.peek(),
+                None | Some('\\' | '\'' | '"' | 'n' | 'r' | 't')
+            ) =>
+            {
+                out.push_str("\\\\")
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
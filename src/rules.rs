@@ -0,0 +1,298 @@
+use serde_json::Value;
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+
+use crate::ast::{Expr, ExprKind};
+use crate::cancel::CancellationToken;
+use crate::error::EvaluationError;
+use crate::expression::Expression;
+use crate::limits::Budget;
+use crate::print::{print, FormatOptions};
+use crate::scope::Scope;
+use crate::{member_chain, Evaluator, Frame};
+
+/// Named compiled expressions evaluated together against one set of variables.
+///
+/// Sub-expressions that appear more than once across the rules, such as the path
+/// `event.user.tier` or the comparison `event.amount > 100`, are evaluated once per
+/// evaluation of the set and their values reused. Only sub-expressions that don't call host
+/// functions are shared, since those may not return the same value twice. A shared value
+/// counts against the [`EvaluationLimits`](crate::EvaluationLimits) of the rule that
+/// computed it; a failure isn't shared, so each rule reports its own error.
+#[derive(Debug)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    /// Number of distinct shared sub-expressions.
+    slots: usize,
+}
+
+#[derive(Debug)]
+struct Rule {
+    name: String,
+    expression: Expression,
+    /// The slot holding the value of each shared node in the rule's tree, by the node's
+    /// address. The addresses are taken once the rule is in place in [`RuleSet::rules`],
+    /// which is never changed afterwards, so they stay valid while the set lives.
+    shared: HashMap<usize, usize>,
+}
+
+/// Whether [`Evaluator::match_rules`] stops at the first matching rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    /// Stop at the first rule that matches, in the order the rules were given.
+    First,
+    /// Evaluate every rule.
+    #[default]
+    All,
+}
+
+/// The outcome of [`Evaluator::match_rules`].
+#[derive(Debug, Default)]
+pub struct RuleMatches<'r> {
+    /// Names of the rules that evaluated to a truthy value, in the order the rules were given.
+    pub matched: Vec<&'r str>,
+    /// Rules that failed to evaluate, which don't match.
+    pub errors: Vec<(&'r str, EvaluationError)>,
+}
+
+/// Values of the shared sub-expressions, for the rule being evaluated.
+#[derive(Clone, Copy)]
+pub(crate) struct Shared<'a> {
+    nodes: &'a HashMap<usize, usize>,
+    values: &'a [OnceCell<Cow<'a, Value>>],
+}
+
+impl<'a> Shared<'a> {
+    /// The slot holding the value of `expr`, if it is shared with other rules.
+    pub(crate) fn slot(&self, expr: &Expr) -> Option<&'a OnceCell<Cow<'a, Value>>> {
+        let address = expr as *const Expr as usize;
+        self.nodes.get(&address).map(|slot| &self.values[*slot])
+    }
+}
+
+impl RuleSet {
+    /// Builds a set from named expressions. A later rule replaces an earlier one with the
+    /// same name.
+    pub fn new<N: Into<String>>(rules: impl IntoIterator<Item = (N, Expression)>) -> Self {
+        let mut named: Vec<Rule> = Vec::new();
+        for (name, expression) in rules {
+            let rule = Rule {
+                name: name.into(),
+                expression,
+                shared: HashMap::new(),
+            };
+            match named.iter_mut().find(|existing| existing.name == rule.name) {
+                Some(existing) => *existing = rule,
+                None => named.push(rule),
+            }
+        }
+
+        // Group the nodes of every rule by their printed form, which ignores spans. No rule
+        // is added or replaced from here on, so the nodes keep their addresses
+        let mut occurrences: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        for (index, rule) in named.iter().enumerate() {
+            let source = rule.expression.source();
+            collect(rule.expression.root(), &mut |node| {
+                let key = print(node, source, &FormatOptions::default());
+                let address = node as *const Expr as usize;
+                occurrences.entry(key).or_default().push((index, address));
+            });
+        }
+
+        let mut slots = 0;
+        for nodes in occurrences.into_values().filter(|nodes| nodes.len() > 1) {
+            for (index, address) in nodes {
+                named[index].shared.insert(address, slots);
+            }
+            slots += 1;
+        }
+
+        RuleSet {
+            rules: named,
+            slots,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Names of the rules, in the order they were given.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|rule| rule.name.as_str())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Expression> {
+        self.rules
+            .iter()
+            .find(|rule| rule.name == name)
+            .map(|rule| &rule.expression)
+    }
+}
+
+/// Calls `shared` with each node of `expr` whose value could be shared: one evaluated on
+/// its own, other than a literal, that doesn't depend on a host function. Returns whether
+/// `expr` itself qualifies.
+fn collect<'e>(expr: &'e Expr, shared: &mut impl FnMut(&'e Expr)) -> bool {
+    let pure = match &expr.kind {
+        ExprKind::Literal(_) => return true,
+        ExprKind::Identifier(_) => true,
+        ExprKind::Member { .. } => {
+            // The objects inside a property chain aren't evaluated on their own
+            let chain = member_chain(expr);
+            match &chain.root.kind {
+                ExprKind::Identifier(_) => true,
+                _ => collect(chain.root, shared),
+            }
+        }
+        ExprKind::Unary { operand, .. } => collect(operand, shared),
+        ExprKind::Binary { left, right, .. } => collect_all([&**left, right], shared),
+        ExprKind::Conditional {
+            test,
+            consequent,
+            alternate,
+        } => collect_all([&**test, consequent, alternate], shared),
+        ExprKind::Call { arguments, .. } => {
+            collect_all(arguments, shared);
+            false
+        }
+        ExprKind::MethodCall {
+            receiver,
+            arguments,
+            ..
+        } => collect_all(std::iter::once(&**receiver).chain(arguments), shared),
+        ExprKind::Unsupported(_) => false,
+    };
+    if pure {
+        shared(expr);
+    }
+    pure
+}
+
+/// Collects from each of `exprs`, even after one that doesn't qualify, and returns whether
+/// they all do.
+fn collect_all<'e>(
+    exprs: impl IntoIterator<Item = &'e Expr>,
+    shared: &mut impl FnMut(&'e Expr),
+) -> bool {
+    exprs
+        .into_iter()
+        .filter(|expr| !collect(expr, shared))
+        .count()
+        == 0
+}
+
+impl Evaluator {
+    /// Evaluates every rule in `rules` against `variables`, reusing the values of
+    /// sub-expressions the rules have in common. Returns the result of each rule by name.
+    pub fn evaluate_rules<'r, S>(
+        &self,
+        rules: &'r RuleSet,
+        variables: &S,
+    ) -> HashMap<&'r str, Result<Value, EvaluationError>>
+    where
+        S: Scope + ?Sized,
+    {
+        self.evaluate_rules_cancellable(rules, variables, &CancellationToken::never())
+    }
+
+    /// Like [`evaluate_rules`](Evaluator::evaluate_rules), but once `token` is cancelled or
+    /// its deadline passes, the rules still being evaluated fail with
+    /// [`ErrorKind::Cancelled`](crate::ErrorKind::Cancelled) or
+    /// [`ErrorKind::Timeout`](crate::ErrorKind::Timeout).
+    pub fn evaluate_rules_cancellable<'r, S>(
+        &self,
+        rules: &'r RuleSet,
+        variables: &S,
+        token: &CancellationToken,
+    ) -> HashMap<&'r str, Result<Value, EvaluationError>>
+    where
+        S: Scope + ?Sized,
+    {
+        let values = shared_values(rules);
+        rules
+            .rules
+            .iter()
+            .map(|rule| {
+                let result = self.evaluate_rule(rule, &values, &variables, token);
+                (rule.name.as_str(), result)
+            })
+            .collect()
+    }
+
+    /// Evaluates the rules in `rules` in order, collecting those whose value is truthy, and
+    /// with [`MatchMode::First`] stopping at the first of them.
+    pub fn match_rules<'r, S>(
+        &self,
+        rules: &'r RuleSet,
+        variables: &S,
+        mode: MatchMode,
+    ) -> RuleMatches<'r>
+    where
+        S: Scope + ?Sized,
+    {
+        self.match_rules_cancellable(rules, variables, mode, &CancellationToken::never())
+    }
+
+    /// Like [`match_rules`](Evaluator::match_rules), but once `token` is cancelled or its
+    /// deadline passes, the rules still being evaluated fail as with
+    /// [`evaluate_rules_cancellable`](Evaluator::evaluate_rules_cancellable).
+    pub fn match_rules_cancellable<'r, S>(
+        &self,
+        rules: &'r RuleSet,
+        variables: &S,
+        mode: MatchMode,
+        token: &CancellationToken,
+    ) -> RuleMatches<'r>
+    where
+        S: Scope + ?Sized,
+    {
+        let values = shared_values(rules);
+        let mut matches = RuleMatches::default();
+        for rule in &rules.rules {
+            match self.evaluate_rule(rule, &values, &variables, token) {
+                Ok(value) => {
+                    if self.to_boolean(&value).unwrap_or(false) {
+                        matches.matched.push(rule.name.as_str());
+                        if mode == MatchMode::First {
+                            break;
+                        }
+                    }
+                }
+                Err(error) => matches.errors.push((rule.name.as_str(), error)),
+            }
+        }
+        matches
+    }
+
+    fn evaluate_rule<'a>(
+        &'a self,
+        rule: &'a Rule,
+        values: &'a [OnceCell<Cow<'a, Value>>],
+        variables: &'a dyn Scope,
+        token: &CancellationToken,
+    ) -> Result<Value, EvaluationError> {
+        let frame = Frame {
+            source: rule.expression.source(),
+            variables,
+            base: &self.scope,
+            budget: Budget::new(self.limits, token),
+            shared: Some(Shared {
+                nodes: &rule.shared,
+                values,
+            }),
+        };
+        self.evaluate_node(rule.expression.root(), &frame)
+            .map(Cow::into_owned)
+            .map_err(|e| EvaluationError::new(e, rule.expression.source()))
+    }
+}
+
+fn shared_values<'a>(rules: &RuleSet) -> Vec<OnceCell<Cow<'a, Value>>> {
+    (0..rules.slots).map(|_| OnceCell::new()).collect()
+}
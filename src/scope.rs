@@ -0,0 +1,151 @@
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::ContextEntry;
+
+static NULL: Value = Value::Null;
+
+/// A source of variables for an evaluation.
+///
+/// Variables passed to [`Evaluator::evaluate_with`](crate::Evaluator::evaluate_with) are
+/// looked up first; names they don't define fall through to the evaluator's own context.
+///
+/// Implement it to resolve variables lazily from the host, e.g. from a cache or a database,
+/// only when an expression references them. Values can be borrowed from the scope or
+/// produced on demand as [`Cow::Owned`].
+pub trait Scope: sealed::AsScope {
+    /// Resolves a top-level variable, or `None` when the scope doesn't define `name`.
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>>;
+
+    /// Resolves a property path such as `user.profile.tier`, given as the root `name`
+    /// (`user`) and the properties that follow it (`["profile", "tier"]`).
+    ///
+    /// Returns `None` only when the root is undefined; a missing property resolves to null.
+    /// The default walks the value returned by [`get`](Scope::get), override it to fetch
+    /// nested values without materialising the whole root.
+    fn get_path(&self, name: &str, path: &[&str]) -> Option<Cow<'_, Value>> {
+        self.get(name).map(|value| walk(value, path))
+    }
+
+    /// Names of the variables this scope defines, used to suggest corrections for unknown
+    /// identifiers. Scopes that can't list their variables keep the default, which is empty.
+    fn names(&self) -> Vec<&str> {
+        Vec::new()
+    }
+}
+
+mod sealed {
+    use super::Scope;
+
+    /// Views any scope as a trait object, including one that already is, such as
+    /// `dyn Scope`, which can't be coerced to one.
+    pub trait AsScope {
+        fn as_scope(&self) -> &dyn Scope;
+    }
+
+    impl<S: Scope> AsScope for S {
+        fn as_scope(&self) -> &dyn Scope {
+            self
+        }
+    }
+}
+
+/// Follows `path` through nested objects, yielding null once a property is missing or the
+/// current value isn't an object.
+pub(crate) fn walk<'a>(value: Cow<'a, Value>, path: &[&str]) -> Cow<'a, Value> {
+    match value {
+        Cow::Borrowed(value) => Cow::Borrowed(
+            path.iter()
+                .try_fold(value, |value, prop| value.as_object()?.get(*prop))
+                .unwrap_or(&NULL),
+        ),
+        Cow::Owned(value) => Cow::Owned(
+            path.iter()
+                .try_fold(value, |value, prop| match value {
+                    Value::Object(mut map) => map.remove(*prop),
+                    _ => None,
+                })
+                .unwrap_or(Value::Null),
+        ),
+    }
+}
+
+impl Scope for HashMap<String, Value> {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        HashMap::get(self, name).map(Cow::Borrowed)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.keys().map(String::as_str).collect()
+    }
+}
+
+impl Scope for HashMap<String, ContextEntry> {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        match HashMap::get(self, name) {
+            Some(ContextEntry::Variable(v)) => Some(Cow::Borrowed(v)),
+            _ => None,
+        }
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.iter()
+            .filter(|(_, entry)| matches!(entry, ContextEntry::Variable(_)))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+impl Scope for Map<String, Value> {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        Map::get(self, name).map(Cow::Borrowed)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.keys().map(String::as_str).collect()
+    }
+}
+
+/// The keys of a JSON object are its variables; any other value defines none.
+impl Scope for Value {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        self.as_object()
+            .and_then(|map| map.get(name))
+            .map(Cow::Borrowed)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.as_object()
+            .map(|map| map.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl<S: Scope + ?Sized> Scope for &S {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        (**self).get(name)
+    }
+
+    fn get_path(&self, name: &str, path: &[&str]) -> Option<Cow<'_, Value>> {
+        (**self).get_path(name, path)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        (**self).names()
+    }
+}
+
+impl<S: Scope + ?Sized> Scope for Box<S> {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        (**self).get(name)
+    }
+
+    fn get_path(&self, name: &str, path: &[&str]) -> Option<Cow<'_, Value>> {
+        (**self).get_path(name, path)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        (**self).names()
+    }
+}
@@ -0,0 +1,68 @@
+use crate::policy::{AccessPolicy, Capability};
+
+/// Picks the candidate closest to `name` by edit distance, if any is close enough to be a
+/// likely typo. Ties go to the candidate that sorts first, so suggestions are stable.
+pub(crate) fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    // Allow roughly one edit per three characters, and always at least one
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance between `a` and `b` in characters, counting a swap of two adjacent
+/// characters as a single edit since that is the most common typo.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+/// Suggests a built-in method of `receiver_type` for an unknown `method`, among those
+/// `policy` allows.
+pub(crate) fn method(receiver_type: &str, method: &str, policy: &AccessPolicy) -> Option<String> {
+    let names = crate::method::methods(receiver_type)
+        .iter()
+        .map(|m| m.name)
+        .filter(|name| policy.allows(Capability::Method, name));
+    did_you_mean(method, names).map(str::to_string)
+}
+
+/// Suggests a registered function for an unknown `function`, among those `policy` allows.
+pub(crate) fn function<'a>(
+    function: &str,
+    names: impl IntoIterator<Item = &'a String>,
+    policy: &AccessPolicy,
+) -> Option<String> {
+    let names = names
+        .into_iter()
+        .map(String::as_str)
+        .filter(|name| policy.allows(Capability::Function, name));
+    did_you_mean(function, names).map(str::to_string)
+}
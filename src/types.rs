@@ -0,0 +1,515 @@
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::ast::{BinaryOp, Expr, ExprKind, UnaryOp};
+use crate::error::{ErrorKind, NodeError};
+use crate::policy::Capability;
+use crate::validate::Diagnostic;
+use crate::{method, suggest, Evaluator};
+
+/// The type of a value, as far as it is known before evaluation.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Type {
+    /// Anything; nothing is checked against it.
+    #[default]
+    Any,
+    Null,
+    Boolean,
+    Number,
+    String,
+    /// An array whose items have the given type.
+    Array(Box<Type>),
+    /// An object with the given properties. Reading a property it doesn't declare is an
+    /// error when it is `closed`, and reads as [`Type::Any`] otherwise.
+    Object {
+        properties: BTreeMap<String, Type>,
+        closed: bool,
+    },
+}
+
+impl Type {
+    pub fn array(items: Type) -> Self {
+        Type::Array(Box::new(items))
+    }
+
+    /// An object declaring `properties`, which may hold others too.
+    pub fn object<S: Into<String>>(properties: impl IntoIterator<Item = (S, Type)>) -> Self {
+        Type::Object {
+            properties: properties
+                .into_iter()
+                .map(|(name, ty)| (name.into(), ty))
+                .collect(),
+            closed: false,
+        }
+    }
+
+    /// The type of `value`. Objects are open, since other values may hold more properties.
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Type::Null,
+            Value::Bool(_) => Type::Boolean,
+            Value::Number(_) => Type::Number,
+            Value::String(_) => Type::String,
+            Value::Array(items) => Type::array(Type::union(items.iter().map(Type::of))),
+            Value::Object(map) => Type::object(map.iter().map(|(k, v)| (k.clone(), Type::of(v)))),
+        }
+    }
+
+    /// Reads the type a JSON Schema describes.
+    ///
+    /// `type`, `properties`, `additionalProperties: false`, `items`, `enum`, `const`, `anyOf`
+    /// and `oneOf` are understood; anything else, such as `$ref`, reads as [`Type::Any`].
+    /// Null is accepted wherever a value is expected, so a nullable type such as
+    /// `["string", "null"]` reads as its non-null part.
+    pub fn from_schema(schema: &Value) -> Self {
+        let Some(schema) = schema.as_object() else {
+            return Type::Any;
+        };
+        if let Some(value) = schema.get("const") {
+            return Type::of(value);
+        }
+        if let Some(Value::Array(values)) = schema.get("enum") {
+            return Type::union(values.iter().map(Type::of));
+        }
+        for key in ["anyOf", "oneOf"] {
+            if let Some(Value::Array(variants)) = schema.get(key) {
+                return Type::union(variants.iter().map(Type::from_schema));
+            }
+        }
+        match schema.get("type") {
+            Some(Value::String(name)) => Type::from_schema_type(name, schema),
+            Some(Value::Array(names)) => Type::union(
+                names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|name| Type::from_schema_type(name, schema)),
+            ),
+            _ if schema.contains_key("properties") => Type::from_schema_type("object", schema),
+            _ => Type::Any,
+        }
+    }
+
+    fn from_schema_type(name: &str, schema: &serde_json::Map<String, Value>) -> Self {
+        match name {
+            "null" => Type::Null,
+            "boolean" => Type::Boolean,
+            "integer" | "number" => Type::Number,
+            "string" => Type::String,
+            "array" => Type::array(schema.get("items").map_or(Type::Any, Type::from_schema)),
+            "object" => Type::Object {
+                properties: schema
+                    .get("properties")
+                    .and_then(Value::as_object)
+                    .map(|properties| {
+                        properties
+                            .iter()
+                            .map(|(name, schema)| (name.clone(), Type::from_schema(schema)))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                closed: schema.get("additionalProperties") == Some(&Value::Bool(false)),
+            },
+            _ => Type::Any,
+        }
+    }
+
+    /// The one type all of `types` share, ignoring nulls, or [`Type::Any`] if they differ.
+    fn union(types: impl IntoIterator<Item = Type>) -> Self {
+        let mut union = None;
+        let mut nullable = false;
+        for ty in types {
+            match (ty, &union) {
+                (Type::Null, _) => nullable = true,
+                (ty, None) => union = Some(ty),
+                (ty, Some(existing)) if ty == *existing => {}
+                _ => return Type::Any,
+            }
+        }
+        match union {
+            Some(ty) => ty,
+            None if nullable => Type::Null,
+            None => Type::Any,
+        }
+    }
+
+    /// The JSON type of values of this type, as used in error messages, unless it is
+    /// [`Type::Any`].
+    fn json_type(&self) -> Option<&'static str> {
+        match self {
+            Type::Any => None,
+            Type::Null => Some("null"),
+            Type::Boolean => Some("boolean"),
+            Type::Number => Some("number"),
+            Type::String => Some("string"),
+            Type::Array(_) => Some("array"),
+            Type::Object { .. } => Some("object"),
+        }
+    }
+
+    /// The type of a built-in method's result, from the name in its signature.
+    fn from_json_type(name: &str) -> Self {
+        match name {
+            "null" => Type::Null,
+            "boolean" => Type::Boolean,
+            "number" => Type::Number,
+            "string" => Type::String,
+            "array" => Type::array(Type::Any),
+            _ => Type::Any,
+        }
+    }
+
+    /// Whether a value of type `other` may be passed where this type is expected.
+    pub fn accepts(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Any, _) | (_, Type::Any) | (_, Type::Null) => true,
+            (Type::Array(expected), Type::Array(found)) => expected.accepts(found),
+            (Type::Object { .. }, Type::Object { .. }) => true,
+            (expected, found) => expected == found,
+        }
+    }
+
+    /// Whether arithmetic and comparisons may convert values of this type to numbers.
+    fn is_numeric(&self) -> bool {
+        !matches!(self, Type::Array(_) | Type::Object { .. })
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Any => f.write_str("any"),
+            Type::Array(items) if **items != Type::Any => write!(f, "array<{}>", items),
+            ty => f.write_str(ty.json_type().unwrap_or("any")),
+        }
+    }
+}
+
+/// Parameter and return types of a host function.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FunctionType {
+    pub params: Vec<Type>,
+    pub returns: Type,
+}
+
+/// Types of the variables and functions expressions may use, for
+/// [`Evaluator::check_types`].
+///
+/// Only declared variables are known: the evaluator's own context or scope is never read
+/// while checking, since its values may be fetched on demand or differ by evaluation.
+#[derive(Debug, Clone, Default)]
+pub struct TypeContext {
+    variables: HashMap<String, Type>,
+    functions: HashMap<String, FunctionType>,
+}
+
+impl TypeContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares the properties of an object schema, e.g. the schema of an event payload, as
+    /// variables.
+    pub fn from_schema(schema: &Value) -> Self {
+        let variables = match Type::from_schema(schema) {
+            Type::Object { properties, .. } => properties.into_iter().collect(),
+            _ => HashMap::new(),
+        };
+        TypeContext {
+            variables,
+            functions: HashMap::new(),
+        }
+    }
+
+    pub fn variable(mut self, name: impl Into<String>, ty: Type) -> Self {
+        self.variables.insert(name.into(), ty);
+        self
+    }
+
+    pub fn function(
+        mut self,
+        name: impl Into<String>,
+        params: impl IntoIterator<Item = Type>,
+        returns: Type,
+    ) -> Self {
+        let params = params.into_iter().collect();
+        self.functions
+            .insert(name.into(), FunctionType { params, returns });
+        self
+    }
+}
+
+impl Evaluator {
+    /// Checks an expression against the types of the variables and functions it uses,
+    /// without evaluating it.
+    ///
+    /// Reports everything [`validate`](Evaluator::validate) does, with method receivers
+    /// typed from `context`, plus undefined variables, properties a closed object doesn't
+    /// declare, operands arithmetic can't convert to numbers and function calls with the
+    /// wrong number or types of arguments. Undeclared properties are errors for every object in strict property mode.
+    pub fn check_types(&self, expression: &str, context: &TypeContext) -> Vec<Diagnostic> {
+        self.type_check(expression, context).1
+    }
+
+    /// Infers the type of an expression's result, or reports why it doesn't type check as
+    /// [`check_types`](Evaluator::check_types) does.
+    pub fn infer_type(
+        &self,
+        expression: &str,
+        context: &TypeContext,
+    ) -> Result<Type, Vec<Diagnostic>> {
+        match self.type_check(expression, context) {
+            (ty, diagnostics) if diagnostics.is_empty() => Ok(ty),
+            (_, diagnostics) => Err(diagnostics),
+        }
+    }
+
+    fn type_check(&self, expression: &str, context: &TypeContext) -> (Type, Vec<Diagnostic>) {
+        let mut result = None;
+        let diagnostics = self.diagnose(expression, |statement, errors| {
+            let mut checker = Checker {
+                evaluator: self,
+                context,
+                source: expression,
+                types: HashMap::new(),
+                errors: Vec::new(),
+            };
+            let ty = checker.infer(statement);
+            result.get_or_insert(ty);
+
+            let receiver_type =
+                |expr: &Expr| checker.types.get(&(expr as *const Expr))?.json_type();
+            statement.walk(&mut |node| self.check(node, errors, &receiver_type));
+            errors.append(&mut checker.errors);
+        });
+        (result.unwrap_or_default(), diagnostics)
+    }
+}
+
+/// Infers the type of every node of an expression, collecting type errors on the way.
+struct Checker<'a> {
+    evaluator: &'a Evaluator,
+    context: &'a TypeContext,
+    source: &'a str,
+    // Keyed by node address, which is stable while the tree is borrowed
+    types: HashMap<*const Expr, Type>,
+    errors: Vec<NodeError>,
+}
+
+impl Checker<'_> {
+    fn infer(&mut self, expr: &Expr) -> Type {
+        let ty = match &expr.kind {
+            ExprKind::Literal(value) => Type::of(value),
+            ExprKind::Identifier(name) => self.variable(name, expr),
+            ExprKind::Member {
+                object,
+                property,
+                optional,
+            } => self.member(expr, object, property, *optional),
+            ExprKind::Unary { op, operand } => {
+                let operand = self.infer(operand);
+                match op {
+                    UnaryOp::Not => Type::Boolean,
+                    UnaryOp::Minus | UnaryOp::Plus => {
+                        if !operand.is_numeric() {
+                            self.type_error(op.as_str(), &operand, None, expr);
+                        }
+                        Type::Number
+                    }
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let left = self.infer(left);
+                let right = self.infer(right);
+                self.binary(*op, &left, &right, expr)
+            }
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => {
+                self.infer(test);
+                let consequent = self.infer(consequent);
+                let alternate = self.infer(alternate);
+                if consequent == alternate {
+                    consequent
+                } else {
+                    Type::Any
+                }
+            }
+            ExprKind::Call {
+                function,
+                arguments,
+            } => {
+                let types: Vec<Type> = arguments.iter().map(|arg| self.infer(arg)).collect();
+                self.call(expr, function, arguments, &types)
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => {
+                let receiver = self.infer(receiver);
+                arguments.iter().for_each(|arg| {
+                    self.infer(arg);
+                });
+                receiver
+                    .json_type()
+                    .and_then(|receiver_type| method::signature(receiver_type, method))
+                    .map_or(Type::Any, |signature| {
+                        Type::from_json_type(signature.returns)
+                    })
+            }
+            ExprKind::Unsupported(_) => Type::Any,
+        };
+        self.types.insert(expr, ty.clone());
+        ty
+    }
+
+    fn lookup(&self, name: &str) -> Option<Type> {
+        self.context.variables.get(name).cloned()
+    }
+
+    fn variable(&mut self, name: &str, expr: &Expr) -> Type {
+        if let Some(ty) = self.lookup(name) {
+            return ty;
+        }
+        // Forbidden globals are reported as such already
+        let policy = &self.evaluator.policy;
+        if policy.allows(Capability::Global, name) {
+            let names = self
+                .context
+                .variables
+                .keys()
+                .map(String::as_str)
+                .filter(|name| policy.allows(Capability::Global, name));
+            let suggestion = suggest::did_you_mean(name, names).map(str::to_string);
+            let kind = ErrorKind::UndefinedVariable {
+                name: name.to_string(),
+            };
+            self.errors
+                .push(NodeError::at(kind, expr.span).with_suggestion(suggestion));
+        }
+        Type::Any
+    }
+
+    fn member(&mut self, expr: &Expr, object: &Expr, property: &str, optional: bool) -> Type {
+        let object_type = match &object.kind {
+            // `a?.b` reads null when `a` is missing
+            ExprKind::Identifier(name) if optional && self.lookup(name).is_none() => {
+                self.types.insert(object, Type::Null);
+                Type::Null
+            }
+            _ => self.infer(object),
+        };
+
+        let strict = self.evaluator.strict_properties && !optional;
+        let declared = match object_type {
+            Type::Any => return Type::Any,
+            Type::Object {
+                mut properties,
+                closed,
+            } => match properties.remove(property) {
+                Some(ty) => return ty,
+                None if optional => return Type::Null,
+                None if !closed && !strict => return Type::Any,
+                None => properties,
+            },
+            // Properties of anything else read as null
+            _ if !strict => return Type::Null,
+            _ => BTreeMap::new(),
+        };
+
+        let names = declared.keys().map(String::as_str);
+        let suggestion = suggest::did_you_mean(property, names).map(str::to_string);
+        let kind = ErrorKind::MissingProperty {
+            path: self.source[object.span.start..object.span.end].to_string(),
+            property: property.to_string(),
+        };
+        self.errors
+            .push(NodeError::at(kind, expr.span).with_suggestion(suggestion));
+        Type::Any
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: &Type, right: &Type, expr: &Expr) -> Type {
+        match op {
+            BinaryOp::Add => match (left, right) {
+                (Type::String, _) | (_, Type::String) => Type::String,
+                (Type::Number, Type::Number) => Type::Number,
+                (Type::Any, _) | (_, Type::Any) => Type::Any,
+                // Anything else is concatenated as strings
+                _ => Type::String,
+            },
+            BinaryOp::Sub
+            | BinaryOp::Mul
+            | BinaryOp::Div
+            | BinaryOp::Rem
+            | BinaryOp::Gt
+            | BinaryOp::Lt
+            | BinaryOp::GtEq
+            | BinaryOp::LtEq => {
+                if !left.is_numeric() || !right.is_numeric() {
+                    self.type_error(op.as_str(), left, Some(right), expr);
+                }
+                match op {
+                    BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => Type::Number,
+                    _ => Type::Boolean,
+                }
+            }
+            _ => Type::Boolean,
+        }
+    }
+
+    fn call(&mut self, expr: &Expr, function: &str, arguments: &[Expr], types: &[Type]) -> Type {
+        let Some(signature) = self.context.functions.get(function) else {
+            return Type::Any;
+        };
+        let params = signature.params.len();
+        if params != arguments.len() {
+            // A registered function of known arity is reported as such already
+            let reported = self
+                .evaluator
+                .functions
+                .get(function)
+                .and_then(|f| f.arity())
+                .is_some_and(|arity| arity != arguments.len());
+            if !reported {
+                let kind = ErrorKind::ArityMismatch {
+                    callee: function.to_string(),
+                    min: params,
+                    max: params,
+                    found: arguments.len(),
+                };
+                self.errors.push(NodeError::at(kind, expr.span));
+            }
+            return signature.returns.clone();
+        }
+        for (position, ((param, arg), expr)) in signature
+            .params
+            .iter()
+            .zip(types)
+            .zip(arguments)
+            .enumerate()
+        {
+            if !param.accepts(arg) {
+                let kind = ErrorKind::ArgumentType {
+                    callee: function.to_string(),
+                    position: position + 1,
+                    expected: Box::new(param.clone()),
+                    found: Box::new(arg.clone()),
+                };
+                self.errors.push(NodeError::at(kind, expr.span));
+            }
+        }
+        signature.returns.clone()
+    }
+
+    fn type_error(&mut self, op: &'static str, left: &Type, right: Option<&Type>, expr: &Expr) {
+        let kind = ErrorKind::TypeError {
+            op,
+            left: left.json_type().unwrap_or("any"),
+            right: right.map(|right| right.json_type().unwrap_or("any")),
+        };
+        self.errors.push(NodeError::at(kind, expr.span));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::ast::{BinaryOp, Expr, ExprKind, UnaryOp};
+use crate::error::{ErrorKind, EvaluationError, NodeError};
+use crate::pattern::PATTERN_METHODS;
+use crate::policy::Capability;
+use crate::{limits, method, parse, suggest, type_name, Evaluator};
+use serde_json::Value;
+
+/// A problem found in an expression by [`Evaluator::validate`].
+pub type Diagnostic = EvaluationError;
+
+impl Evaluator {
+    /// Checks an expression without evaluating it, reporting every problem found rather than
+    /// stopping at the first one.
+    ///
+    /// Reports syntax errors, unsupported constructs, unknown functions, calls with the wrong
+    /// number of arguments and methods that do not exist on receivers whose type is known
+    /// from the expression alone. No variables are needed, so an expression with no
+    /// diagnostics can still fail at evaluation time, e.g. on a missing variable.
+    pub fn validate(&self, expression: &str) -> Vec<Diagnostic> {
+        self.diagnose(expression, |statement, errors| {
+            statement.walk(&mut |node| self.check(node, errors, &static_type));
+        })
+    }
+
+    /// Parses `expression` and runs `check` over each statement, returning the syntax errors
+    /// and whatever `check` reports, in source order.
+    pub(crate) fn diagnose(
+        &self,
+        expression: &str,
+        mut check: impl FnMut(&Expr, &mut Vec<NodeError>),
+    ) -> Vec<Diagnostic> {
+        if let Err(error) = limits::check_depth(expression, self.limits.max_depth) {
+            return vec![EvaluationError::new(error, expression)];
+        }
+
+        let parsed = parse::parse_all(expression);
+        let mut errors = parsed.errors;
+
+        for statement in &parsed.statements {
+            check(statement, &mut errors);
+        }
+
+        errors.sort_by_key(|error| error.span.map(|span| (span.start, span.end)));
+        errors
+            .into_iter()
+            .map(|error| EvaluationError::new(error, expression))
+            .collect()
+    }
+
+    /// Checks a single node, using `receiver_type` for the JSON type method receivers are
+    /// known to evaluate to.
+    pub(crate) fn check(
+        &self,
+        node: &Expr,
+        errors: &mut Vec<NodeError>,
+        receiver_type: &dyn Fn(&Expr) -> Option<&'static str>,
+    ) {
+        let forbidden = match &node.kind {
+            ExprKind::Identifier(name) => self.policy.check(Capability::Global, name),
+            ExprKind::Call { function, .. } => self.policy.check(Capability::Function, function),
+            ExprKind::MethodCall { method, .. } => self.policy.check(Capability::Method, method),
+            _ => Ok(()),
+        };
+        if let Err(kind) = forbidden {
+            errors.push(NodeError::at(kind, node.span));
+            return;
+        }
+
+        let mut suggestion = None;
+        let kind = match &node.kind {
+            // Parts missing from a broken tree have an empty span and are reported as
+            // syntax errors already
+            ExprKind::Unsupported(construct) if node.span.start < node.span.end => {
+                ErrorKind::Unsupported {
+                    construct: construct.clone(),
+                }
+            }
+            ExprKind::Call {
+                function,
+                arguments,
+            } => match self.functions.get(function) {
+                None => {
+                    suggestion = suggest::function(function, self.functions.keys(), &self.policy);
+                    ErrorKind::UndefinedFunction {
+                        name: function.clone(),
+                    }
+                }
+                Some(f) => match f.arity().filter(|&arity| arity != arguments.len()) {
+                    Some(arity) => ErrorKind::ArityMismatch {
+                        callee: function.clone(),
+                        min: arity,
+                        max: arity,
+                        found: arguments.len(),
+                    },
+                    None => return,
+                },
+            },
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => {
+                if let Err(error) = self.check_pattern(method, arguments) {
+                    errors.push(error);
+                }
+                let Some(receiver_type) = receiver_type(receiver) else {
+                    return;
+                };
+                match method::signature(receiver_type, method) {
+                    None => {
+                        suggestion = suggest::method(receiver_type, method, &self.policy);
+                        ErrorKind::UnknownMethod {
+                            receiver_type,
+                            method: method.clone(),
+                        }
+                    }
+                    Some(signature) if !signature.accepts(arguments.len()) => {
+                        ErrorKind::ArityMismatch {
+                            callee: method.clone(),
+                            min: signature.min_args,
+                            max: signature.max_args,
+                            found: arguments.len(),
+                        }
+                    }
+                    Some(_) => return,
+                }
+            }
+            _ => return,
+        };
+        errors.push(NodeError::at(kind, node.span).with_suggestion(suggestion));
+    }
+
+    /// Checks the pattern passed to a method taking a regular expression against the regex
+    /// policy, compiling literal patterns ahead of their first evaluation.
+    fn check_pattern(&self, method: &str, arguments: &[Expr]) -> Result<(), NodeError> {
+        self.regexes.policy().check_call(method, arguments)?;
+        match arguments.first() {
+            Some(Expr {
+                kind: ExprKind::Literal(Value::String(pattern)),
+                span,
+            }) if PATTERN_METHODS.contains(&method) => self
+                .regexes
+                .get(method, pattern)
+                .map(drop)
+                .map_err(|kind| NodeError::at(kind, *span)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// JSON type `expr` evaluates to whatever the variables hold, if that is known.
+pub(crate) fn static_type(expr: &Expr) -> Option<&'static str> {
+    match &expr.kind {
+        ExprKind::Literal(value) => Some(type_name(value)),
+        ExprKind::Unary { op, .. } => match op {
+            UnaryOp::Not => Some("boolean"),
+            UnaryOp::Minus | UnaryOp::Plus => Some("number"),
+        },
+        ExprKind::Binary { op, left, right } => match op {
+            BinaryOp::Add => match (static_type(left), static_type(right)) {
+                (Some("string"), _) | (_, Some("string")) => Some("string"),
+                (Some("number"), Some("number")) => Some("number"),
+                _ => None,
+            },
+            BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => Some("number"),
+            _ => Some("boolean"),
+        },
+        ExprKind::Conditional {
+            consequent,
+            alternate,
+            ..
+        } => static_type(consequent).filter(|&ty| static_type(alternate) == Some(ty)),
+        ExprKind::MethodCall {
+            receiver, method, ..
+        } => static_type(receiver)
+            .and_then(|receiver_type| method::signature(receiver_type, method))
+            .map(|signature| signature.returns),
+        _ => None,
+    }
+}
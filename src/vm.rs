@@ -0,0 +1,406 @@
+use serde_json::Value;
+use std::borrow::Cow;
+
+use crate::ast::{BinaryOp, Expr, ExprKind, Span, UnaryOp};
+use crate::cancel::CancellationToken;
+use crate::error::{ErrorKind, EvaluationError, NodeError};
+use crate::expression::Expression;
+use crate::limits::Budget;
+use crate::policy::Capability;
+use crate::scope::Scope;
+use crate::{member_chain, Evaluator, Frame, MemberChain};
+
+/// An [`Expression`] lowered to bytecode for a small stack machine, for expressions
+/// evaluated often enough that walking the tree each time shows.
+///
+/// Evaluating a program with [`Evaluator::evaluate_program`] gives the same result, or the
+/// same error at the same place, as evaluating the expression with
+/// [`Evaluator::evaluate_expression`]: the operations are shared, and nodes are counted
+/// against the [`EvaluationLimits`](crate::EvaluationLimits) in the same order. What it saves
+/// is the recursion, and taking apart property chains such as `a.b.c` on every evaluation.
+///
+/// A program borrows the expression it was lowered from.
+#[derive(Debug, Clone)]
+pub struct Program<'e> {
+    source: &'e str,
+    code: Vec<Instruction<'e>>,
+    chains: Vec<MemberChain<'e>>,
+    /// Most values on the stack at once.
+    max_stack: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Instruction<'e> {
+    /// Enters a node whose operands are evaluated by the instructions that follow, checking
+    /// that the function or method it calls, if any, may be called.
+    Enter {
+        span: Span,
+        callee: Option<(Capability, &'e str)>,
+    },
+    /// Leaves a conditional, whose result is already on the stack.
+    Leave,
+    Literal {
+        value: &'e Value,
+        span: Span,
+    },
+    Variable {
+        name: &'e str,
+        span: Span,
+    },
+    /// Reads a property chain starting at a variable, such as `a.b.c`.
+    Path {
+        chain: usize,
+        name: &'e str,
+        span: Span,
+    },
+    /// Reads a property chain from the value on top of the stack, such as `f().b.c`.
+    Member {
+        chain: usize,
+        span: Span,
+    },
+    Unary {
+        op: UnaryOp,
+        span: Span,
+    },
+    Binary {
+        op: BinaryOp,
+        span: Span,
+    },
+    /// Pops the test of a conditional and jumps to its alternate if the test is falsy.
+    JumpUnless(usize),
+    Jump(usize),
+    Call {
+        node: &'e Expr,
+        function: &'e str,
+        argc: usize,
+    },
+    /// Checks the pattern of a call to a regex method, after its receiver and before its
+    /// arguments are evaluated.
+    CheckPattern {
+        method: &'e str,
+        arguments: &'e [Expr],
+    },
+    MethodCall {
+        node: &'e Expr,
+        receiver: &'e Expr,
+        method: &'e str,
+        argc: usize,
+    },
+    Unsupported {
+        construct: &'e str,
+        span: Span,
+    },
+}
+
+impl<'e> Program<'e> {
+    /// Lowers `expression` to bytecode.
+    pub fn compile(expression: &'e Expression) -> Self {
+        let mut program = Program {
+            source: expression.source(),
+            code: Vec::new(),
+            chains: Vec::new(),
+            max_stack: 0,
+        };
+        let mut depth = 0;
+        program.lower(expression.root(), &mut depth);
+        program
+    }
+
+    /// The source text of the expression the program was lowered from.
+    pub fn source(&self) -> &'e str {
+        self.source
+    }
+
+    /// Appends the instructions evaluating `expr`, in the order the tree-walker visits its
+    /// nodes. `depth` tracks the values on the stack.
+    fn lower(&mut self, expr: &'e Expr, depth: &mut usize) {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Literal(value) => {
+                self.code.push(Instruction::Literal { value, span });
+                self.pushed(depth);
+            }
+            ExprKind::Identifier(name) => {
+                self.code.push(Instruction::Variable { name, span });
+                self.pushed(depth);
+            }
+            ExprKind::Member { .. } => {
+                let chain = member_chain(expr);
+                let root = chain.root;
+                let index = self.chains.len();
+                self.chains.push(chain);
+                match &root.kind {
+                    // The variable is read along with its properties, as one node
+                    ExprKind::Identifier(name) => {
+                        self.code.push(Instruction::Path {
+                            chain: index,
+                            name,
+                            span,
+                        });
+                        self.pushed(depth);
+                    }
+                    _ => {
+                        self.enter(span, None);
+                        self.lower(root, depth);
+                        self.code.push(Instruction::Member { chain: index, span });
+                    }
+                }
+            }
+            ExprKind::Unary { op, operand } => {
+                self.enter(span, None);
+                self.lower(operand, depth);
+                self.code.push(Instruction::Unary { op: *op, span });
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.enter(span, None);
+                self.lower(left, depth);
+                self.lower(right, depth);
+                self.code.push(Instruction::Binary { op: *op, span });
+                *depth -= 1;
+            }
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => {
+                self.enter(span, None);
+                self.lower(test, depth);
+                let jump_unless = self.code.len();
+                self.code.push(Instruction::JumpUnless(0));
+                *depth -= 1;
+
+                self.lower(consequent, depth);
+                let jump = self.code.len();
+                self.code.push(Instruction::Jump(0));
+                // Only one of the branches leaves its value on the stack
+                *depth -= 1;
+
+                self.code[jump_unless] = Instruction::JumpUnless(self.code.len());
+                self.lower(alternate, depth);
+                self.code[jump] = Instruction::Jump(self.code.len());
+                self.code.push(Instruction::Leave);
+            }
+            ExprKind::Call {
+                function,
+                arguments,
+            } => {
+                self.enter(span, Some((Capability::Function, function)));
+                for argument in arguments {
+                    self.lower(argument, depth);
+                }
+                self.code.push(Instruction::Call {
+                    node: expr,
+                    function,
+                    argc: arguments.len(),
+                });
+                *depth -= arguments.len();
+                self.pushed(depth);
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => {
+                self.enter(span, Some((Capability::Method, method)));
+                self.lower(receiver, depth);
+                self.code
+                    .push(Instruction::CheckPattern { method, arguments });
+                for argument in arguments {
+                    self.lower(argument, depth);
+                }
+                self.code.push(Instruction::MethodCall {
+                    node: expr,
+                    receiver,
+                    method,
+                    argc: arguments.len(),
+                });
+                *depth -= arguments.len();
+            }
+            ExprKind::Unsupported(construct) => {
+                self.code.push(Instruction::Unsupported { construct, span });
+                self.pushed(depth);
+            }
+        }
+    }
+
+    fn enter(&mut self, span: Span, callee: Option<(Capability, &'e str)>) {
+        self.code.push(Instruction::Enter { span, callee });
+    }
+
+    fn pushed(&mut self, depth: &mut usize) {
+        *depth += 1;
+        self.max_stack = self.max_stack.max(*depth);
+    }
+}
+
+impl Evaluator {
+    /// Evaluates a [`Program`], with the same result as evaluating the expression it was
+    /// lowered from with [`evaluate_expression`](Evaluator::evaluate_expression).
+    pub fn evaluate_program<S>(
+        &self,
+        program: &Program<'_>,
+        variables: &S,
+    ) -> Result<Value, EvaluationError>
+    where
+        S: Scope + ?Sized,
+    {
+        self.evaluate_program_cancellable(program, variables, &CancellationToken::never())
+    }
+
+    /// Like [`evaluate_program`](Evaluator::evaluate_program), but stops with
+    /// [`ErrorKind::Cancelled`] or [`ErrorKind::Timeout`] once `token` is cancelled or its
+    /// deadline passes, as [`evaluate_cancellable`](Evaluator::evaluate_cancellable) does.
+    pub fn evaluate_program_cancellable<S>(
+        &self,
+        program: &Program<'_>,
+        variables: &S,
+        token: &CancellationToken,
+    ) -> Result<Value, EvaluationError>
+    where
+        S: Scope + ?Sized,
+    {
+        let frame = Frame {
+            source: program.source,
+            variables: &variables,
+            base: &self.scope,
+            budget: Budget::new(self.limits, token),
+            shared: None,
+        };
+        let result = self
+            .run(program, &frame)
+            .map_err(|e| EvaluationError::new(e, program.source))?;
+
+        #[cfg(feature = "logging")]
+        self.logger.trace(&format!("Result: {}", result));
+
+        Ok(result.into_owned())
+    }
+
+    fn run<'a>(
+        &self,
+        program: &'a Program<'_>,
+        frame: &Frame<'a>,
+    ) -> Result<Cow<'a, Value>, NodeError> {
+        let mut stack: Vec<Cow<'a, Value>> = Vec::with_capacity(program.max_stack);
+        let mut pc = 0;
+
+        while let Some(instruction) = program.code.get(pc) {
+            pc += 1;
+            // Each node is entered before its operands and left once its value is pushed,
+            // as the tree-walker does, so limits trip at the same node
+            let value = match instruction {
+                Instruction::Enter { span, callee } => {
+                    enter(frame, *span)?;
+                    if let Some((capability, name)) = callee {
+                        self.policy
+                            .check(*capability, name)
+                            .map_err(|kind| NodeError::at(kind, *span))?;
+                    }
+                    continue;
+                }
+                Instruction::Leave => {
+                    frame.budget.leave();
+                    continue;
+                }
+                Instruction::Literal { value, span } => {
+                    enter(frame, *span)?;
+                    Cow::Borrowed(*value)
+                }
+                Instruction::Variable { name, span } => {
+                    enter(frame, *span)?;
+                    self.evaluate_by_name(name, frame)
+                        .map_err(|e| e.or_span(*span))?
+                }
+                Instruction::Path { chain, name, span } => {
+                    enter(frame, *span)?;
+                    self.variable_member(&program.chains[*chain], name, frame)
+                        .map_err(|e| e.or_span(*span))?
+                }
+                Instruction::Member { chain, span } => {
+                    let object = pop(&mut stack);
+                    self.member_of(&program.chains[*chain], object, frame)
+                        .map_err(|e| e.or_span(*span))?
+                }
+                Instruction::Unary { op, span } => {
+                    let operand = pop(&mut stack);
+                    self.apply_unary(*op, &operand)
+                        .and_then(|value| frame.produced(value))
+                        .map_err(|e| e.or_span(*span))?
+                }
+                Instruction::Binary { op, span } => {
+                    let right = pop(&mut stack);
+                    let left = pop(&mut stack);
+                    self.apply_binary(*op, &left, &right)
+                        .and_then(|value| frame.produced(value))
+                        .map_err(|e| e.or_span(*span))?
+                }
+                Instruction::JumpUnless(target) => {
+                    let test = pop(&mut stack);
+                    if !self.to_boolean(&test)? {
+                        pc = *target;
+                    }
+                    continue;
+                }
+                Instruction::Jump(target) => {
+                    pc = *target;
+                    continue;
+                }
+                Instruction::Call {
+                    node,
+                    function,
+                    argc,
+                } => {
+                    let args = arguments(&mut stack, *argc);
+                    self.call_function(node, function, args, frame)
+                        .and_then(|value| frame.produced(value))
+                        .map_err(|e| e.or_span(node.span))?
+                }
+                Instruction::CheckPattern { method, arguments } => {
+                    self.regexes.policy().check_call(method, arguments)?;
+                    continue;
+                }
+                Instruction::MethodCall {
+                    node,
+                    receiver,
+                    method,
+                    argc,
+                } => {
+                    let args = arguments(&mut stack, *argc);
+                    let object = pop(&mut stack);
+                    self.call_method(node, receiver, method, &object, args, frame)
+                        .and_then(|value| frame.produced(value))
+                        .map_err(|e| e.or_span(node.span))?
+                }
+                Instruction::Unsupported { construct, span } => {
+                    enter(frame, *span)?;
+                    let kind = ErrorKind::Unsupported {
+                        construct: construct.to_string(),
+                    };
+                    return Err(NodeError::at(kind, *span));
+                }
+            };
+            stack.push(value);
+            frame.budget.leave();
+        }
+
+        Ok(pop(&mut stack))
+    }
+}
+
+fn enter(frame: &Frame<'_>, span: Span) -> Result<(), NodeError> {
+    frame.budget.enter().map_err(|e| e.or_span(span))
+}
+
+fn pop<'a>(stack: &mut Vec<Cow<'a, Value>>) -> Cow<'a, Value> {
+    stack
+        .pop()
+        .expect("lowered code pushes each operand before using it")
+}
+
+/// Pops the last `argc` values, in the order they were pushed.
+fn arguments(stack: &mut Vec<Cow<'_, Value>>, argc: usize) -> Vec<Value> {
+    stack
+        .drain(stack.len() - argc..)
+        .map(Cow::into_owned)
+        .collect()
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_borrow)]
+
 use std::collections::HashMap;
 use exprimo::{ContextEntry, Evaluator};
 
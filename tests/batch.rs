@@ -0,0 +1,174 @@
+use exprimo::{CancellationToken, Columns, ErrorKind, Evaluator};
+use serde_json::{json, Value};
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::with_scope(json!({ "threshold": 100 }))
+}
+
+fn rows(count: usize) -> Vec<Value> {
+    (0..count)
+        .map(|i| {
+            let country = if i % 3 == 0 { "NZ" } else { "AU" };
+            json!({ "event": { "amount": i * 10, "country": country } })
+        })
+        .collect()
+}
+
+#[test]
+fn test_evaluate_batch() {
+    let evaluator = evaluator();
+    let expression = evaluator
+        .compile("event.amount > threshold ? event.country : 100 / event.amount")
+        .unwrap();
+
+    let results = evaluator.evaluate_batch(&expression, &rows(13));
+    assert_eq!(results.len(), 13);
+    // Row 0 divides by zero, the others match evaluating each row on its own
+    assert_eq!(
+        results[0].as_ref().unwrap_err().to_string(),
+        "Division by zero (line 1, column 44)"
+    );
+    assert_eq!(results[5].as_ref().unwrap(), &json!(2.0));
+    assert_eq!(results[12].as_ref().unwrap(), &json!("NZ"));
+    for (row, result) in rows(13).iter().zip(&results).skip(1) {
+        let expected = evaluator.evaluate_expression(&expression, row).unwrap();
+        assert_eq!(result.as_ref().unwrap(), &expected);
+    }
+}
+
+#[test]
+fn test_select() {
+    let evaluator = evaluator();
+    let expression = evaluator
+        .compile("event.amount >= threshold && event.country == 'NZ'")
+        .unwrap();
+
+    // More than one word of rows
+    let selection = evaluator.select(&expression, &rows(200));
+    assert_eq!(selection.len(), 200);
+    assert_eq!(selection.words().len(), 4);
+    let expected: Vec<usize> = (10..200).filter(|i| i % 3 == 0).collect();
+    assert_eq!(selection.iter().collect::<Vec<_>>(), expected);
+    assert_eq!(selection.count(), expected.len());
+    assert!(selection.contains(12));
+    assert!(!selection.contains(13));
+    assert!(!selection.contains(200));
+    assert!(selection.errors().is_empty());
+
+    assert!(evaluator.select(&expression, &rows(0)).is_empty());
+}
+
+#[test]
+fn test_select_errors() {
+    let evaluator = evaluator();
+    let expression = evaluator.compile("1 / event.amount > 0.05").unwrap();
+
+    let selection = evaluator.select(&expression, &rows(5));
+    assert_eq!(selection.iter().collect::<Vec<_>>(), [1]);
+    // Failed rows aren't selected
+    assert_eq!(selection.errors().len(), 1);
+    assert_eq!(selection.errors()[0].0, 0);
+}
+
+#[test]
+fn test_columns() {
+    let evaluator = evaluator();
+    let amounts = [json!(120), json!(80), json!(300)];
+    let countries = [json!("NZ"), json!("NZ")];
+    let columns = Columns::new()
+        .column("amount", &amounts)
+        .column("country", &countries);
+    assert_eq!(columns.len(), 3);
+
+    let expression = evaluator
+        .compile("amount > threshold && country == 'NZ'")
+        .unwrap();
+    let selection = evaluator.select(&expression, &columns.rows());
+    assert_eq!(selection.iter().collect::<Vec<_>>(), [0]);
+
+    // The shorter column reads as null past its end
+    let expression = evaluator.compile("country").unwrap();
+    let results = evaluator.evaluate_batch(&expression, &columns.rows());
+    assert_eq!(results[2].as_ref().unwrap(), &Value::Null);
+
+    // Missing columns are undefined, with suggestions from the others
+    let expression = evaluator.compile("amout > 1").unwrap();
+    let results = evaluator.evaluate_batch(&expression, &columns.rows());
+    assert_eq!(
+        results[0].as_ref().unwrap_err().suggestion(),
+        Some("amount")
+    );
+}
+
+#[test]
+fn test_cancellation() {
+    let token = CancellationToken::new();
+    let mut evaluator = evaluator();
+    let stopper = token.clone();
+    evaluator.register_fn("stop", move || {
+        stopper.cancel();
+        true
+    });
+    let expression = evaluator
+        .compile("event.amount == 50 ? stop() : event.amount >= 0")
+        .unwrap();
+
+    // The rows after the one cancelling the token fail
+    let results = evaluator.evaluate_batch_cancellable(&expression, &rows(10), &token);
+    assert!(results[..5].iter().all(|result| result.is_ok()));
+    assert!(results[6..]
+        .iter()
+        .all(|result| matches!(result.as_ref().unwrap_err().kind(), ErrorKind::Cancelled)));
+
+    let selection = evaluator.select_cancellable(&expression, &rows(100), &token);
+    assert_eq!(selection.len(), 100);
+    assert_eq!(selection.count(), 0);
+    assert_eq!(selection.errors().len(), 100);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_parallel_cancellation() {
+    let evaluator = evaluator();
+    let expression = evaluator.compile("event.amount >= 0").unwrap();
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let results = evaluator.par_evaluate_batch_cancellable(&expression, &rows(200), &token);
+    assert!(results
+        .iter()
+        .all(|result| matches!(result.as_ref().unwrap_err().kind(), ErrorKind::Cancelled)));
+
+    let selection = evaluator.par_select_cancellable(&expression, &rows(200), &token);
+    assert_eq!(selection.count(), 0);
+    assert_eq!(selection.errors().len(), 200);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_parallel_matches_sequential() {
+    let evaluator = evaluator();
+    let rows = rows(1000);
+
+    let expression = evaluator
+        .compile("event.amount >= threshold && event.country == 'NZ' || 1 / event.amount > 1")
+        .unwrap();
+    let sequential = evaluator.select(&expression, &rows);
+    let parallel = evaluator.par_select(&expression, &rows);
+    assert_eq!(parallel.words(), sequential.words());
+    assert_eq!(parallel.len(), sequential.len());
+    assert_eq!(parallel.errors().len(), 1);
+    assert_eq!(parallel.errors()[0].0, 0);
+
+    let sequential = evaluator.evaluate_batch(&expression, &rows);
+    let parallel = evaluator.par_evaluate_batch(&expression, &rows);
+    assert_eq!(parallel.len(), sequential.len());
+    for (parallel, sequential) in parallel.iter().zip(&sequential) {
+        assert_eq!(
+            parallel.as_ref().map_err(|e| e.to_string()),
+            sequential.as_ref().map_err(|e| e.to_string())
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use exprimo::{CancellationToken, ErrorKind, EvaluationLimits, Evaluator};
+use serde_json::Value;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    let mut evaluator = common::evaluator(&[("a", Value::from(1))]);
+    evaluator.register_fn("sleep", |ms: u64| {
+        thread::sleep(Duration::from_millis(ms));
+        true
+    });
+    // Spins until the evaluation is stopped, as a long computation checking its token would
+    evaluator.register_cancellable("wait", |_, token: &CancellationToken| {
+        while !token.should_stop() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        Err("stopped")
+    });
+    evaluator
+}
+
+#[test]
+fn test_timeout_limit() {
+    let mut evaluator = evaluator();
+    evaluator.set_limits(EvaluationLimits {
+        timeout: Some(Duration::from_millis(10)),
+        ..EvaluationLimits::default()
+    });
+
+    let error = evaluator.evaluate("sleep(50) && a > 0").unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::Timeout));
+    assert_eq!(error.message(), "Evaluation timed out");
+
+    // A host function that overruns the deadline fails even when nothing follows it
+    let error = evaluator.evaluate("sleep(50)").unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::Timeout));
+
+    assert_eq!(
+        evaluator.evaluate("sleep(0) && a > 0").unwrap(),
+        Value::Bool(true)
+    );
+}
+
+#[test]
+fn test_token_deadline() {
+    let evaluator = evaluator();
+    let expression = evaluator.compile("wait() || a > 0").unwrap();
+
+    let started = Instant::now();
+    let token = CancellationToken::new().with_timeout(Duration::from_millis(10));
+    let error = evaluator
+        .evaluate_cancellable(&expression, &Value::Null, &token)
+        .unwrap_err();
+
+    assert!(matches!(error.kind(), ErrorKind::Timeout));
+    assert_eq!(error.span().start, 0);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_cancel_from_another_thread() {
+    let evaluator = evaluator();
+    let expression = evaluator.compile("wait()").unwrap();
+    let token = CancellationToken::new();
+
+    let canceller = {
+        let token = token.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            token.cancel();
+        })
+    };
+    let error = evaluator
+        .evaluate_cancellable(&expression, &Value::Null, &token)
+        .unwrap_err();
+    canceller.join().unwrap();
+
+    assert!(matches!(error.kind(), ErrorKind::Cancelled));
+    assert_eq!(error.message(), "Evaluation was cancelled");
+    assert!(token.is_cancelled());
+}
+
+#[test]
+fn test_cancelled_before_evaluation() {
+    let evaluator = evaluator();
+    let expression = evaluator.compile("a + 1").unwrap();
+
+    let token = CancellationToken::new();
+    assert_eq!(
+        evaluator
+            .evaluate_cancellable(&expression, &Value::Null, &token)
+            .unwrap(),
+        Value::from(2.0)
+    );
+
+    token.cancel();
+    let error = evaluator
+        .evaluate_cancellable(&expression, &Value::Null, &token)
+        .unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::Cancelled));
+}
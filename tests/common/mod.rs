@@ -0,0 +1,43 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use exprimo::{ContextEntry, Evaluator, Scope};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[cfg(feature = "logging")]
+use scribe_rust::Logger;
+
+/// An evaluator whose context holds `variables`.
+pub fn evaluator(variables: &[(&str, Value)]) -> Evaluator {
+    with_context(
+        variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), ContextEntry::Variable(value.clone())))
+            .collect(),
+    )
+}
+
+/// An evaluator with `context`, which may hold host functions as well as variables.
+pub fn with_context(context: HashMap<String, ContextEntry>) -> Evaluator {
+    #[cfg(feature = "logging")]
+    let logger = Logger::default();
+
+    Evaluator::new(
+        context,
+        #[cfg(feature = "logging")]
+        logger,
+    )
+}
+
+/// An evaluator whose base variables are resolved by `scope`.
+pub fn with_scope(scope: impl Scope + Send + Sync + 'static) -> Evaluator {
+    #[cfg(feature = "logging")]
+    let logger = Logger::default();
+
+    Evaluator::with_scope(
+        scope,
+        #[cfg(feature = "logging")]
+        logger,
+    )
+}
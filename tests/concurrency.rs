@@ -0,0 +1,67 @@
+use exprimo::{Evaluator, Expression};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+mod common;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_evaluator_is_send_sync() {
+    assert_send_sync::<Evaluator>();
+    assert_send_sync::<Expression>();
+}
+
+#[test]
+fn test_shared_evaluator() {
+    let mut evaluator = common::evaluator(&[("threshold", Value::from(10))]);
+    evaluator.register_fn("double", |x: f64| x * 2.0);
+
+    let evaluator = Arc::new(evaluator);
+    let expression = Arc::new(evaluator.compile("double(amount) > threshold").unwrap());
+
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let evaluator = Arc::clone(&evaluator);
+            let expression = Arc::clone(&expression);
+            thread::spawn(move || {
+                let mut variables = HashMap::new();
+                variables.insert("amount".to_string(), Value::from(i));
+                evaluator
+                    .evaluate_expression(&expression, &variables)
+                    .unwrap()
+            })
+        })
+        .collect();
+
+    let results: Vec<Value> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    for (i, result) in results.iter().enumerate() {
+        assert_eq!(*result, Value::Bool(i * 2 > 10));
+    }
+}
+
+#[test]
+fn test_variables_shadow_context() {
+    let evaluator = common::evaluator(&[("a", Value::from(1)), ("b", Value::from(2))]);
+
+    let expression = evaluator.compile("a + b").unwrap();
+
+    let mut variables = HashMap::new();
+    variables.insert("a".to_string(), Value::from(40));
+
+    assert_eq!(
+        evaluator
+            .evaluate_expression(&expression, &variables)
+            .unwrap(),
+        42.0
+    );
+    assert_eq!(
+        evaluator
+            .evaluate_expression(&expression, &HashMap::<String, Value>::new())
+            .unwrap(),
+        3.0
+    );
+}
@@ -0,0 +1,63 @@
+use exprimo::{Dependencies, Evaluator};
+use std::collections::BTreeSet;
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::evaluator(&[])
+}
+
+fn set<'a>(items: impl IntoIterator<Item = &'a str>) -> BTreeSet<String> {
+    items.into_iter().map(str::to_string).collect()
+}
+
+#[test]
+fn test_dependencies() {
+    let evaluator = evaluator();
+
+    let dependencies = evaluator
+        .dependencies(
+            "event.user.id == lookup(event.items, threshold) && event?.user?.name.startsWith('A')",
+        )
+        .unwrap();
+    assert_eq!(
+        dependencies.variables,
+        set([
+            "event.items",
+            "event.user.id",
+            "event.user.name",
+            "threshold"
+        ])
+    );
+    assert_eq!(dependencies.functions, set(["lookup"]));
+    assert_eq!(
+        dependencies.roots(),
+        ["event", "threshold"].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_dependencies_of_computed_values() {
+    let evaluator = evaluator();
+
+    // Properties of a function's result aren't read from the variables
+    let dependencies = evaluator
+        .dependencies("score(user).total > (limits.max ? limits.max : fallback())")
+        .unwrap();
+    assert_eq!(dependencies.variables, set(["limits.max", "user"]));
+    assert_eq!(dependencies.functions, set(["fallback", "score"]));
+
+    assert_eq!(
+        evaluator.dependencies("1 + 2 * 'a'.length").unwrap(),
+        Dependencies::default()
+    );
+}
+
+#[test]
+fn test_dependencies_of_compiled_expression() {
+    let evaluator = evaluator();
+    let expression = evaluator.compile("!user.active").unwrap();
+
+    assert_eq!(expression.dependencies().variables, set(["user.active"]));
+    assert!(evaluator.dependencies("a +").is_err());
+}
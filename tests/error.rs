@@ -0,0 +1,114 @@
+use exprimo::{ErrorKind, EvaluationError};
+use serde_json::Value;
+
+mod common;
+
+fn evaluate_err(expression: &str) -> EvaluationError {
+    let mut evaluator = common::evaluator(&[("a", Value::from(1)), ("s", Value::from("x"))]);
+    evaluator.try_register_fn("fail", |reason: String| Err::<f64, _>(reason));
+
+    evaluator.evaluate(expression).unwrap_err()
+}
+
+#[test]
+fn test_error_spans() {
+    let err1 = evaluate_err("a + a / 0");
+    let err2 = evaluate_err("a + missing");
+    let err3 = evaluate_err("1 + s * 2");
+    let err4 = evaluate_err("a > 0 ? [1, 2] : 3");
+
+    assert_eq!(err1.message(), "Division by zero");
+    assert_eq!((err1.span().start, err1.span().end), (4, 9));
+    assert_eq!((err2.span().start, err2.span().end), (4, 11));
+    assert_eq!(err2.column(), 5);
+    assert_eq!((err3.span().start, err3.span().end), (4, 9));
+    assert_eq!(
+        &err4.expression()[err4.span().start..err4.span().end],
+        "[1, 2]"
+    );
+}
+
+#[test]
+fn test_error_line_and_column() {
+    let err = evaluate_err("a +\n  missing");
+
+    assert_eq!(err.line(), 2);
+    assert_eq!(err.column(), 3);
+    assert_eq!(
+        err.to_string(),
+        "Identifier 'missing' not found in context. (line 2, column 3)"
+    );
+}
+
+#[test]
+fn test_caret_rendering() {
+    let err1 = evaluate_err("a + a / 0");
+    let err2 = evaluate_err("a ==\n  (s\n  * 2)");
+
+    assert_eq!(
+        format!("{:#}", err1),
+        "error: Division by zero\n --> 1:5\n  |\n1 | a + a / 0\n  |     ^^^^^"
+    );
+    assert_eq!(
+        format!("{:#}", err2),
+        "error: Cannot apply '*' to string and number\n --> 2:4\n  |\n2 |   (s\n  |    ^\n3 |   * 2)\n  | ^^^^^"
+    );
+}
+
+#[test]
+fn test_error_kinds() {
+    let err1 = evaluate_err("a + missing");
+    let err2 = evaluate_err("s * 2");
+    let err3 = evaluate_err("-s");
+    let err4 = evaluate_err("s.reverse()");
+    let err5 = evaluate_err("a.trim()");
+    let err6 = evaluate_err("s.substring(true)");
+    let err7 = evaluate_err("fail('boom')");
+    let err8 = evaluate_err("nope(1)");
+    let err9 = evaluate_err("a in s");
+    let err10 = evaluate_err("");
+
+    assert!(matches!(err1.kind(), ErrorKind::UndefinedVariable { name } if name == "missing"));
+    assert!(matches!(
+        err2.kind(),
+        ErrorKind::TypeError {
+            op: "*",
+            left: "string",
+            right: Some("number")
+        }
+    ));
+    assert!(matches!(
+        err3.kind(),
+        ErrorKind::TypeError {
+            op: "-",
+            left: "string",
+            right: None
+        }
+    ));
+    assert!(matches!(
+        err4.kind(),
+        ErrorKind::UnknownMethod { receiver_type: "string", method } if method == "reverse"
+    ));
+    assert!(matches!(
+        err5.kind(),
+        ErrorKind::UnknownMethod {
+            receiver_type: "number",
+            ..
+        }
+    ));
+    assert!(
+        matches!(err6.kind(), ErrorKind::InvalidArguments { method, .. } if method == "substring")
+    );
+    assert!(matches!(err7.kind(), ErrorKind::HostFunction { name, .. } if name == "fail"));
+    assert!(matches!(err8.kind(), ErrorKind::UndefinedFunction { name } if name == "nope"));
+    assert!(
+        matches!(err9.kind(), ErrorKind::Unsupported { construct } if construct == "binary operator 'in'")
+    );
+    assert!(matches!(err10.kind(), ErrorKind::Parse { .. }));
+
+    assert_eq!(err2.message(), "Cannot apply '*' to string and number");
+    assert_eq!(
+        std::error::Error::source(&err7).unwrap().to_string(),
+        "boom"
+    );
+}
@@ -0,0 +1,84 @@
+use exprimo::{ContextEntry, Evaluator, HostFunction};
+use serde_json::Value;
+use std::collections::HashMap;
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::evaluator(&[("x", Value::from(15.0))])
+}
+
+#[test]
+fn test_typed_function() {
+    let mut evaluator = evaluator();
+    evaluator
+        .register_fn("clamp", |x: f64, lo: f64, hi: f64| x.max(lo).min(hi))
+        .register_fn("repeat", |s: String, n: usize| s.repeat(n))
+        .register_fn("answer", || 42);
+
+    assert_eq!(evaluator.evaluate("clamp(x, 0, 10)").unwrap(), 10.0);
+    assert_eq!(evaluator.evaluate("repeat('ab', 3)").unwrap(), "ababab");
+    assert_eq!(evaluator.evaluate("answer() + 1").unwrap(), 43.0);
+}
+
+#[test]
+fn test_typed_function_errors() {
+    let mut evaluator = evaluator();
+    evaluator.register_fn("clamp", |x: f64, lo: f64, hi: f64| x.max(lo).min(hi));
+
+    let err = evaluator.evaluate("clamp(x, 0)").unwrap_err();
+    assert!(err.to_string().contains("expected 3 argument(s), got 2"));
+
+    let err = evaluator.evaluate("clamp(x, 'a', 10)").unwrap_err();
+    assert!(err.to_string().contains("argument 2"));
+}
+
+#[test]
+fn test_fallible_function() {
+    let mut evaluator = evaluator();
+    evaluator.try_register_fn("sqrt", |x: f64| {
+        if x < 0.0 {
+            Err(format!("cannot take the square root of {}", x))
+        } else {
+            Ok(x.sqrt())
+        }
+    });
+
+    assert_eq!(evaluator.evaluate("sqrt(16)").unwrap(), 4.0);
+
+    let err = evaluator.evaluate("sqrt(0 - 4)").unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("Error calling function 'sqrt'"));
+    assert!(message.contains("cannot take the square root of -4"));
+}
+
+#[test]
+fn test_raw_function() {
+    let mut context = HashMap::new();
+    context.insert(
+        "first".to_string(),
+        ContextEntry::HostFunction(HostFunction::new(|args: Vec<Value>| {
+            args.into_iter().next().ok_or("no arguments")
+        })),
+    );
+
+    let mut evaluator = common::with_context(context);
+    evaluator.register_function("sum", |args: Vec<Value>| {
+        args.iter()
+            .map(|v| {
+                v.as_f64()
+                    .ok_or_else(|| anyhow::anyhow!("not a number: {}", v))
+            })
+            .sum::<anyhow::Result<f64>>()
+            .map(Value::from)
+    });
+
+    assert_eq!(evaluator.evaluate("first('a', 'b')").unwrap(), "a");
+    assert_eq!(evaluator.evaluate("sum(1, 2, 3)").unwrap(), 6.0);
+    assert!(evaluator.evaluate("first()").is_err());
+    assert!(evaluator
+        .evaluate("sum(1, 'b')")
+        .unwrap_err()
+        .to_string()
+        .contains("not a number: \"b\""));
+}
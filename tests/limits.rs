@@ -0,0 +1,134 @@
+use exprimo::{ErrorKind, EvaluationLimits, Evaluator, Limit};
+use serde_json::Value;
+
+mod common;
+
+fn evaluator(limits: EvaluationLimits) -> Evaluator {
+    let mut evaluator = common::evaluator(&[("a", Value::from(1))]);
+    evaluator.register_fn("f", |x: Value| x);
+    evaluator.set_limits(limits);
+    evaluator
+}
+
+fn exceeded(evaluator: &Evaluator, expression: &str) -> Option<Limit> {
+    match evaluator.evaluate(expression).unwrap_err().kind() {
+        ErrorKind::LimitExceeded { limit, .. } => Some(*limit),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_deep_nesting_is_rejected_before_parsing() {
+    let evaluator = evaluator(EvaluationLimits::default());
+    let n = 100_000;
+
+    let inputs = [
+        format!("{}a{}", "(".repeat(n), ")".repeat(n)),
+        vec!["a"; n].join(" + "),
+        format!("{}a", "!".repeat(n)),
+        format!("{}a", "- ".repeat(n)),
+        format!("{}a", "a ? a : ".repeat(n)),
+        format!("{}a{}", "f(".repeat(n), ")".repeat(n)),
+        format!("a{}", ".b".repeat(n)),
+        format!("{}1{}", "[".repeat(n), "]".repeat(n)),
+        // A quote in a comment doesn't start a string hiding the brackets after it
+        format!("/* ' */ {}a{}", "(".repeat(n), ")".repeat(n)),
+        format!("// '\n{}a{}", "(".repeat(n), ")".repeat(n)),
+        // Nor does one in a string that ends at the line
+        format!("'\n{}a{}", "(".repeat(n), ")".repeat(n)),
+    ];
+
+    for input in &inputs {
+        assert_eq!(exceeded(&evaluator, input), Some(Limit::Depth));
+        assert_eq!(evaluator.validate(input).len(), 1);
+    }
+}
+
+#[test]
+fn test_nesting_within_limit() {
+    let evaluator = evaluator(EvaluationLimits::default());
+
+    let expression = format!("{}a{}", "(1 + ".repeat(60), ")".repeat(60));
+    let calls = format!("{}a{}", "f(".repeat(100), ")".repeat(100));
+    let strings = format!("'{}' + a", "((((".repeat(100));
+    let comments = format!("/* {} */ a // {}", "((((".repeat(100), "((((".repeat(100));
+
+    assert_eq!(evaluator.evaluate(&expression).unwrap(), 61.0);
+    assert_eq!(evaluator.evaluate(&calls).unwrap(), 1);
+    assert!(evaluator.evaluate(&strings).is_ok());
+    assert_eq!(evaluator.evaluate(&comments).unwrap(), 1);
+}
+
+#[test]
+fn test_step_limit() {
+    let evaluator = evaluator(EvaluationLimits {
+        max_steps: 10,
+        ..EvaluationLimits::default()
+    });
+
+    assert!(evaluator.evaluate("a + a + a").is_ok());
+    assert_eq!(
+        exceeded(&evaluator, "a + a + a + a + a + a"),
+        Some(Limit::Steps)
+    );
+}
+
+#[test]
+fn test_output_size_limits() {
+    let evaluator = evaluator(EvaluationLimits {
+        max_string_length: 16,
+        max_array_length: 3,
+        max_allocation: 256,
+        ..EvaluationLimits::default()
+    });
+
+    assert_eq!(
+        exceeded(&evaluator, "'aaaaaaaaaa' + 'bbbbbbbbbb'"),
+        Some(Limit::StringLength)
+    );
+    assert_eq!(
+        exceeded(&evaluator, "'aaaa'.replace('a', 'xxxxxxxx')"),
+        Some(Limit::StringLength)
+    );
+    assert_eq!(
+        exceeded(&evaluator, "'a,b,c,d'.split(',')"),
+        Some(Limit::ArrayLength)
+    );
+    assert_eq!(
+        exceeded(&evaluator, &["'abcdefgh'.toUpperCase()"; 10].join(" == ")),
+        Some(Limit::Allocation)
+    );
+
+    let err = evaluator
+        .evaluate("'aaaa'.replace('a', 'xxxxxxxx')")
+        .unwrap_err();
+    assert_eq!(err.message(), "string length limit of 16 exceeded");
+    assert_eq!(err.span().start, 0);
+}
+
+#[test]
+fn test_untrusted_input_does_not_panic() {
+    let evaluator = evaluator(EvaluationLimits::default());
+
+    let err1 = evaluator.evaluate("a % 0").unwrap_err();
+    let err2 = evaluator.evaluate("1e308 * 10").unwrap_err();
+
+    assert!(matches!(
+        err1.kind(),
+        ErrorKind::NonFiniteNumber { op: "%" }
+    ));
+    assert!(matches!(
+        err2.kind(),
+        ErrorKind::NonFiniteNumber { op: "*" }
+    ));
+    assert_eq!(evaluator.evaluate("'abc'.substring(5)").unwrap(), "");
+    assert_eq!(evaluator.evaluate("'héllo'.substring(3, 1)").unwrap(), "é");
+    assert!(evaluator.evaluate("'héllo'.substring(2)").is_err());
+    // substring takes the byte offsets indexOf returns
+    assert_eq!(
+        evaluator
+            .evaluate("'héllo'.substring('héllo'.indexOf('l'))")
+            .unwrap(),
+        "llo"
+    );
+}
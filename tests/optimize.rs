@@ -0,0 +1,137 @@
+use exprimo::Evaluator;
+use serde_json::json;
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::evaluator(&[("env", json!("prod"))])
+}
+
+fn optimized(evaluator: &Evaluator, expression: &str) -> String {
+    let expression = evaluator.compile(expression).unwrap();
+    evaluator.optimize(&expression).to_string()
+}
+
+#[test]
+fn test_constant_folding() {
+    let evaluator = evaluator();
+
+    assert_eq!(
+        optimized(&evaluator, "(\"prod\" == \"prod\") && x > 10 * 60"),
+        "x > 600"
+    );
+    // Same concatenation as evaluating, numbers included
+    assert_eq!(optimized(&evaluator, "'a' + 1 + 2"), "'a1.02.0'");
+    assert_eq!(optimized(&evaluator, "x + (1 + 2)"), "x + 3");
+    assert_eq!(optimized(&evaluator, "' Hi '.trim().toUpperCase()"), "'HI'");
+    assert_eq!(optimized(&evaluator, "-(2 * 3) - x"), "-6 - x");
+    // Negative zero keeps its sign
+    assert_eq!(optimized(&evaluator, "-(0 * 1) + x"), "-0 + x");
+    // Variables from the context aren't constants, they may be replaced
+    assert_eq!(optimized(&evaluator, "env == 'prod'"), "env == 'prod'");
+    // Host functions are never called
+    assert_eq!(optimized(&evaluator, "now() > 1 + 1"), "now() > 2");
+}
+
+#[test]
+fn test_dead_branches() {
+    let evaluator = evaluator();
+
+    assert_eq!(optimized(&evaluator, "1 > 2 ? a : b + 1"), "b + 1");
+    assert_eq!(optimized(&evaluator, "'' ? a : (true ? c : d)"), "c");
+    assert_eq!(optimized(&evaluator, "x ? 1 + 1 : 'n'"), "x ? 2 : 'n'");
+}
+
+#[test]
+fn test_boolean_identities() {
+    let evaluator = evaluator();
+
+    assert_eq!(optimized(&evaluator, "false || x == 1"), "x == 1");
+    assert_eq!(optimized(&evaluator, "!!(x > 1) && true"), "x > 1");
+    assert_eq!(optimized(&evaluator, "user.active && false"), "false");
+    assert_eq!(optimized(&evaluator, "true || a.b == c.d"), "true");
+    // `&&` returns a boolean, so a non-boolean operand is kept
+    assert_eq!(optimized(&evaluator, "true && x"), "true && x");
+    // Dropping an operand that may fail would hide the error
+    assert_eq!(optimized(&evaluator, "false && f(x)"), "false && f(x)");
+    assert_eq!(optimized(&evaluator, "false && -x"), "false && -x");
+}
+
+#[test]
+fn test_failing_constants_are_kept() {
+    let evaluator = evaluator();
+
+    let expression = evaluator.compile("x == 1 || 1 / 0 > 1").unwrap();
+    let optimized = evaluator.optimize(&expression);
+    assert_eq!(optimized.to_string(), "x == 1 || 1 / 0 > 1");
+    assert_eq!(
+        evaluator
+            .evaluate_expression(&optimized, &json!({ "x": 1 }))
+            .unwrap_err()
+            .to_string(),
+        evaluator
+            .evaluate_expression(&expression, &json!({ "x": 1 }))
+            .unwrap_err()
+            .to_string()
+    );
+}
+
+#[test]
+fn test_printing() {
+    let evaluator = evaluator();
+    let print = |expression| evaluator.compile(expression).unwrap().to_string();
+
+    assert_eq!(print("(a + b) * c - (d - e)"), "(a + b) * c - (d - e)");
+    assert_eq!(print("a || b && c"), "a || b && c");
+    assert_eq!(print("(a || b) && !(c == d)"), "(a || b) && !(c == d)");
+    assert_eq!(
+        print("(a ? b : c) ? d : e ? f : g"),
+        "(a ? b : c) ? d : e ? f : g"
+    );
+    assert_eq!(print("-(-a)"), "-(-a)");
+    assert_eq!(
+        print("user?.name.trim().length()"),
+        "user?.name.trim().length()"
+    );
+    assert_eq!(print("max(a, 1.5, \"it's\")"), "max(a, 1.5, \"it's\")");
+}
+
+#[test]
+fn test_optimized_evaluates_the_same() {
+    let evaluator = evaluator();
+    let variables = json!({ "x": 700, "user": { "active": true }, "a": 1, "b": 2 });
+
+    for source in [
+        "(\"prod\" == \"prod\") && x > 10 * 60",
+        "1 > 2 ? a : b + 1",
+        "'total: ' + (x + 2 * 3)",
+        "false || user.active == true",
+        "' x '.trim() + x",
+        "!!(x > 1) && true",
+    ] {
+        let expression = evaluator.compile(source).unwrap();
+        let optimized = evaluator.optimize(&expression);
+        assert_eq!(
+            evaluator
+                .evaluate_expression(&optimized, &variables)
+                .unwrap(),
+            evaluator
+                .evaluate_expression(&expression, &variables)
+                .unwrap(),
+            "{}",
+            source
+        );
+        // The printed form compiles back to the same result
+        let reparsed = evaluator.compile(&optimized.to_string()).unwrap();
+        assert_eq!(
+            evaluator
+                .evaluate_expression(&reparsed, &variables)
+                .unwrap(),
+            evaluator
+                .evaluate_expression(&expression, &variables)
+                .unwrap(),
+            "{}",
+            optimized
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use exprimo::ast::{BinaryOp, Expr, ExprKind, Span};
+use exprimo::{ErrorKind, Evaluator};
+use serde_json::json;
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::evaluator(&[])
+}
+
+fn parse(expression: &str) -> Expr {
+    evaluator().compile(expression).unwrap().root().clone()
+}
+
+fn span(expr: &Expr) -> (usize, usize) {
+    (expr.span.start, expr.span.end)
+}
+
+/// Expressions covering the supported subset and what lies just outside it.
+const CORPUS: &[&str] = &[
+    "a + b * c - d / e",
+    "a % b * c",
+    "(a + b) * (c - (d))",
+    "a && b || !c && d",
+    "a == b != c === d !== e",
+    "a < b <= c > d >= e",
+    "-a + +b - -(-c)",
+    "!!a.b?.c",
+    "a ? b : c ? d : e",
+    "(a ? b : c) ? d : e",
+    "a ? b ? c : d : e",
+    "f(a, b.c, 'x')",
+    "f()",
+    "a.b.c(d, e).f.g()",
+    "'str'.toUpperCase().length()",
+    "1.5e3 + .5 + 10 - 0.25",
+    "\"double\" + 'single' + 'it\\'s'",
+    "true && false || null",
+    "a\n  && b // trailing comment\n  && c",
+    "/* leading */ a.b",
+    "a + b;",
+    "a?.b?.c == null ? 'none' : a.b.c",
+    "(((a)))",
+    "a in b",
+    "a ?? b",
+    "a ** b",
+    "a & b | c ^ d",
+    "a << 2",
+    "typeof a == 'string'",
+    "[1, 2, 3]",
+    "a[0]",
+    "a = 1",
+    "x => x + 1",
+    "(a, b) => a",
+    "`template`",
+    "new Date()",
+    "a++",
+    "0x1F + 1",
+    "8 / 4 % 3",
+    "2 * 5 % 3",
+    "a * b % c",
+];
+
+/// Expressions in the corpus that rslint_parser parses differently, with the value each
+/// parser's tree evaluates to. rslint_parser binds `%` tighter than `*` and `/`, where
+/// JavaScript and the built-in parser give them the same precedence, left to right.
+#[cfg(feature = "rslint")]
+const RSLINT_DIVERGENCES: &[(&str, f64, f64)] = &[
+    ("8 / 4 % 3", 2.0, 8.0),
+    ("2 * 5 % 3", 1.0, 4.0),
+    ("a * b % c", 1.0, 4.0),
+];
+
+#[test]
+fn test_precedence_and_spans() {
+    let root = parse("a + (b * c) - d");
+    let ExprKind::Binary {
+        op: BinaryOp::Sub,
+        left,
+        right,
+    } = &root.kind
+    else {
+        panic!("{:?}", root);
+    };
+    assert_eq!(span(&root), (0, 15));
+    // The parentheses belong to the enclosing operation, not the grouped expression
+    assert_eq!(span(left), (0, 11));
+    assert_eq!(span(right), (14, 15));
+    let ExprKind::Binary { right: grouped, .. } = &left.kind else {
+        panic!("{:?}", left);
+    };
+    assert_eq!(span(grouped), (5, 10));
+
+    let root = parse("  user?.name.trim( ) ");
+    assert_eq!(span(&root), (2, 20));
+    let ExprKind::MethodCall { receiver, .. } = &root.kind else {
+        panic!("{:?}", root);
+    };
+    assert_eq!(receiver.span, Span::new(2, 12));
+}
+
+#[test]
+fn test_left_associativity() {
+    let evaluator = evaluator();
+
+    assert_eq!(evaluator.evaluate("8 / 4 % 3").unwrap(), json!(2.0));
+    assert_eq!(evaluator.evaluate("10 - 4 - 3").unwrap(), json!(3.0));
+    assert_eq!(evaluator.evaluate("2 * 6 / 3 % 5").unwrap(), json!(4.0));
+}
+
+#[test]
+fn test_literals() {
+    let evaluator = evaluator();
+
+    assert_eq!(evaluator.evaluate("1e3 + .5 + 2.").unwrap(), json!(1002.5));
+    assert_eq!(evaluator.evaluate("true ?.5:1").unwrap(), json!(0.5));
+    assert_eq!(
+        evaluator.evaluate("'a' /* b */ + \"c\"").unwrap(),
+        json!("ac")
+    );
+    assert!(matches!(
+        &parse("0x1F").kind,
+        ExprKind::Unsupported(construct) if construct == "literal 0x1F"
+    ));
+}
+
+#[test]
+fn test_unsupported_constructs() {
+    for (expression, construct, expected_span) in [
+        ("a > 0 ? 1 : [1, [2]]", "array literal", (12, 20)),
+        ("a in b", "binary operator 'in'", (0, 6)),
+        ("typeof a", "unary operator 'typeof'", (0, 8)),
+        ("a[0].b", "computed member access", (0, 4)),
+        ("x => x + 1", "arrow function", (0, 10)),
+        ("a = b", "assignment", (0, 5)),
+        ("`a${b}`", "template literal", (0, 7)),
+        ("f(a, ...b)", "spread argument", (5, 9)),
+    ] {
+        let mut found = None;
+        parse(expression).walk(&mut |node| {
+            if let ExprKind::Unsupported(description) = &node.kind {
+                found.get_or_insert((description.clone(), span(node)));
+            }
+        });
+        assert_eq!(
+            found,
+            Some((construct.to_string(), expected_span)),
+            "{}",
+            expression
+        );
+    }
+}
+
+#[test]
+fn test_syntax_error_messages() {
+    let evaluator = evaluator();
+    let error = |expression| evaluator.compile(expression).unwrap_err();
+
+    for (expression, message, expected_span) in [
+        ("a +", "Expected an expression, but found none", (3, 3)),
+        ("(a", "Expected `)`, but found none", (2, 2)),
+        ("f(a b)", "Expected `)`, but found `b`", (4, 5)),
+        ("a.", "Expected a property name, but found none", (2, 2)),
+        ("1 +* 2", "Expected an expression, but found `*`", (3, 4)),
+        ("a )", "Unexpected `)`", (2, 3)),
+        ("'open", "Unterminated string literal", (0, 5)),
+        ("a # b", "Unexpected character `#`", (2, 3)),
+    ] {
+        let error = error(expression);
+        assert!(matches!(error.kind(), ErrorKind::Parse { .. }));
+        assert_eq!(error.message(), message, "{}", expression);
+        assert_eq!(
+            (error.span().start, error.span().end),
+            expected_span,
+            "{}",
+            expression
+        );
+    }
+
+    // Every syntax error is collected, and the rest of the tree is still checked
+    let diagnostics = evaluator.validate("(a +) + f(,) + nope(1)");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message())
+            .collect::<Vec<_>>(),
+        [
+            "Expected an expression, but found `)`",
+            "Function 'f' not found in context",
+            "Expected an expression, but found `,`",
+            "Function 'nope' not found in context",
+        ]
+    );
+}
+
+#[test]
+fn test_corpus_parses() {
+    let evaluator = evaluator();
+    for expression in CORPUS {
+        // Everything in the corpus parses, whether or not it can be evaluated
+        evaluator.compile(expression).unwrap();
+    }
+}
+
+/// Blanks the descriptions of unsupported constructs, which the parsers word differently.
+#[cfg(feature = "rslint")]
+fn normalise(expr: &Expr) -> Expr {
+    let normalised = |expr: &Expr| Box::new(normalise(expr));
+    let kind = match &expr.kind {
+        ExprKind::Unsupported(_) => ExprKind::Unsupported(String::new()),
+        ExprKind::Literal(_) | ExprKind::Identifier(_) => expr.kind.clone(),
+        ExprKind::Member {
+            object,
+            property,
+            optional,
+        } => ExprKind::Member {
+            object: normalised(object),
+            property: property.clone(),
+            optional: *optional,
+        },
+        ExprKind::Unary { op, operand } => ExprKind::Unary {
+            op: *op,
+            operand: normalised(operand),
+        },
+        ExprKind::Binary { op, left, right } => ExprKind::Binary {
+            op: *op,
+            left: normalised(left),
+            right: normalised(right),
+        },
+        ExprKind::Conditional {
+            test,
+            consequent,
+            alternate,
+        } => ExprKind::Conditional {
+            test: normalised(test),
+            consequent: normalised(consequent),
+            alternate: normalised(alternate),
+        },
+        ExprKind::Call {
+            function,
+            arguments,
+        } => ExprKind::Call {
+            function: function.clone(),
+            arguments: arguments.iter().map(normalise).collect(),
+        },
+        ExprKind::MethodCall {
+            receiver,
+            method,
+            arguments,
+        } => ExprKind::MethodCall {
+            receiver: normalised(receiver),
+            method: method.clone(),
+            arguments: arguments.iter().map(normalise).collect(),
+        },
+    };
+    Expr::new(kind, expr.span)
+}
+
+#[cfg(feature = "rslint")]
+#[test]
+fn test_parity_with_rslint() {
+    let evaluator = evaluator();
+
+    let diverges = |expression: &str| {
+        RSLINT_DIVERGENCES
+            .iter()
+            .any(|(divergent, _, _)| *divergent == expression)
+    };
+    for expression in CORPUS.iter().filter(|expression| !diverges(expression)) {
+        let ours = evaluator.compile(expression).unwrap();
+        let rslint = evaluator.compile_with_rslint(expression).unwrap();
+        assert_eq!(
+            normalise(ours.root()),
+            normalise(rslint.root()),
+            "{}",
+            expression
+        );
+    }
+
+    let variables = json!({ "a": 2, "b": 5, "c": 3 });
+    for (expression, ours, theirs) in RSLINT_DIVERGENCES {
+        let ours_tree = evaluator.compile(expression).unwrap();
+        let rslint_tree = evaluator.compile_with_rslint(expression).unwrap();
+        assert_ne!(normalise(ours_tree.root()), normalise(rslint_tree.root()));
+        assert_eq!(
+            evaluator
+                .evaluate_expression(&ours_tree, &variables)
+                .unwrap(),
+            json!(ours),
+            "{}",
+            expression
+        );
+        assert_eq!(
+            evaluator
+                .evaluate_expression(&rslint_tree, &variables)
+                .unwrap(),
+            json!(theirs),
+            "{}",
+            expression
+        );
+    }
+
+    for expression in [
+        "a +", "(a", "a ? b", "1 +* 2", "a b", "a)", "a; b", "", "f(a b)",
+    ] {
+        assert!(evaluator.compile(expression).is_err(), "{}", expression);
+        assert!(
+            evaluator.compile_with_rslint(expression).is_err(),
+            "{}",
+            expression
+        );
+    }
+}
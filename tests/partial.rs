@@ -0,0 +1,233 @@
+use exprimo::{Evaluator, Partial};
+use serde_json::{json, Value};
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::evaluator(&[("limit", json!(5))])
+}
+
+fn residual(partial: Partial) -> String {
+    match partial {
+        Partial::Residual(expression) => expression.to_string(),
+        other => panic!("expected a residual, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_partial_evaluation() {
+    let evaluator = evaluator();
+    let expression = evaluator.compile("tenant == \"a\" && event.x > 3").unwrap();
+
+    let known = json!({ "tenant": "a" });
+    assert_eq!(
+        residual(evaluator.partial_evaluate(&expression, &known)),
+        "event.x > 3"
+    );
+
+    // A rule that can never match reduces to `false`, unless `event.x > 3` fails
+    let known = json!({ "tenant": "b" });
+    let Partial::ValueUnlessFails { value, residual } =
+        evaluator.partial_evaluate(&expression, &known)
+    else {
+        panic!("expected a value unless the residual fails");
+    };
+    assert_eq!(value, json!(false));
+    assert_eq!(residual.to_string(), "false && event.x > 3");
+
+    let known = json!({ "tenant": "a", "event": { "x": 4 } });
+    assert_eq!(
+        evaluator.partial_evaluate(&expression, &known).value(),
+        Some(&json!(true))
+    );
+}
+
+#[test]
+fn test_known_values() {
+    let evaluator = evaluator();
+    let known = json!({
+        "env": { "name": "prod", "regions": ["eu", "us"] },
+        "factor": 2
+    });
+    let partial = |source| {
+        let expression = evaluator.compile(source).unwrap();
+        evaluator.partial_evaluate(&expression, &known)
+    };
+
+    assert_eq!(
+        residual(partial("env.name == 'prod' ? amount * factor : amount")),
+        "amount * 2"
+    );
+    assert_eq!(
+        partial("env.regions.join(',').contains('eu') || user.admin").value(),
+        Some(&json!(true))
+    );
+    assert_eq!(
+        residual(partial("env.name.toUpperCase() + ':' + user.id")),
+        "'PROD:' + user.id"
+    );
+    // Missing properties of known variables read as null
+    assert_eq!(partial("env.owner == null").value(), Some(&json!(true)));
+    // The evaluator's context may be overridden by the variables given later
+    assert_eq!(residual(partial("x < limit")), "x < limit");
+}
+
+#[test]
+fn test_residual_evaluation() {
+    let evaluator = evaluator();
+    let expression = evaluator
+        .compile("region == 'eu' && (score(user) > threshold || user.vip)")
+        .unwrap();
+    let mut evaluator = evaluator;
+    evaluator.register_fn("score", |user: serde_json::Value| {
+        user["points"].as_f64().unwrap_or_default()
+    });
+
+    let Partial::Residual(residual) =
+        evaluator.partial_evaluate(&expression, &json!({ "region": "eu", "threshold": 10 }))
+    else {
+        panic!("expected a residual");
+    };
+    assert_eq!(residual.to_string(), "score(user) > 10 || user.vip");
+
+    let variables = json!({ "user": { "points": 12, "vip": false } });
+    assert_eq!(
+        evaluator
+            .evaluate_expression(&residual, &variables)
+            .unwrap(),
+        json!(true)
+    );
+}
+
+#[test]
+fn test_failures_are_left_in_the_residual() {
+    let mut evaluator = evaluator();
+    evaluator.set_strict_properties(true);
+    let expression = evaluator.compile("config.mode == 'a' || ok").unwrap();
+
+    let partial = evaluator.partial_evaluate(&expression, &json!({ "config": {} }));
+    assert_eq!(residual(partial), "config.mode == 'a' || ok");
+}
+
+#[test]
+fn test_dropped_operands_may_still_fail() {
+    let evaluator = evaluator();
+    let partial = |source, known| {
+        let expression = evaluator.compile(source).unwrap();
+        evaluator.partial_evaluate(&expression, &known)
+    };
+
+    let Partial::ValueUnlessFails { value, residual } =
+        partial("nul && (true).length()", json!({ "nul": null }))
+    else {
+        panic!("expected a value unless the residual fails");
+    };
+    assert_eq!(value, json!(false));
+    // The residual fails as evaluating the whole expression does
+    let error = evaluator
+        .evaluate_expression(&residual, &json!({}))
+        .unwrap_err();
+    let expected = evaluator
+        .evaluate_expression(
+            &evaluator.compile("nul && (true).length()").unwrap(),
+            &json!({ "nul": null }),
+        )
+        .unwrap_err();
+    assert_eq!(error.to_string(), expected.to_string());
+
+    // A test decided by dropping `x` doesn't pick a branch in the residual
+    let Partial::ValueUnlessFails { value, residual } = partial(
+        "(x || 'a') ? user.name : 1",
+        json!({ "user": { "name": "ann" } }),
+    ) else {
+        panic!("expected a value unless the residual fails");
+    };
+    assert_eq!(value, json!("ann"));
+    assert_eq!(residual.to_string(), "x || 'a' ? 'ann' : 1");
+    assert!(evaluator
+        .evaluate_expression(&residual, &json!({}))
+        .is_err());
+    assert_eq!(
+        evaluator
+            .evaluate_expression(&residual, &json!({ "x": 0 }))
+            .unwrap(),
+        json!("ann")
+    );
+}
+
+#[test]
+fn test_residuals_recompile() {
+    let mut evaluator = evaluator();
+    evaluator.register_fn("f", |event: Value| event["x"].as_f64().unwrap_or_default());
+
+    for (source, known, rest) in [
+        (
+            "tags.join(sep)",
+            json!({ "tags": ["a", "b"] }),
+            json!({ "sep": "-" }),
+        ),
+        (
+            "f(event) == y",
+            json!({ "event": { "x": 1 } }),
+            json!({ "y": 1 }),
+        ),
+        (
+            "x > 0 ? config.tags : config",
+            json!({ "config": { "tags": ["a"] } }),
+            json!({ "x": 1 }),
+        ),
+        (
+            "env.tags.join('-') + n",
+            json!({ "env": { "tags": ["a", "b"] } }),
+            json!({ "n": "!" }),
+        ),
+        // An integer parses back as a float, which a string prints with a `.0`
+        (
+            "count + unit",
+            json!({ "count": 2 }),
+            json!({ "unit": "x" }),
+        ),
+    ] {
+        let expression = evaluator.compile(source).unwrap();
+        let mut variables = known.clone();
+        variables
+            .as_object_mut()
+            .unwrap()
+            .extend(rest.as_object().unwrap().clone());
+        let expected = evaluator
+            .evaluate_expression(&expression, &variables)
+            .unwrap();
+
+        let Partial::Residual(residual) = evaluator.partial_evaluate(&expression, &known) else {
+            panic!("expected a residual for {}", source);
+        };
+        // Arrays and objects stay behind their variables, so the printed residual parses
+        let printed = evaluator.compile(&residual.to_string()).unwrap();
+        assert_eq!(
+            evaluator.evaluate_expression(&printed, &variables).unwrap(),
+            expected,
+            "{}",
+            source
+        );
+        assert_eq!(
+            evaluator
+                .evaluate_expression(&residual, &variables)
+                .unwrap(),
+            expected,
+            "{}",
+            source
+        );
+    }
+
+    let expression = evaluator.compile("tags.join(sep)").unwrap();
+    let partial = evaluator.partial_evaluate(&expression, &json!({ "tags": ["a", "b"] }));
+    assert_eq!(residual(partial), "tags.join(sep)");
+    let expression = evaluator.compile("env.tags.join('-') + n").unwrap();
+    let partial =
+        evaluator.partial_evaluate(&expression, &json!({ "env": { "tags": ["a", "b"] } }));
+    assert_eq!(residual(partial), "'a-b' + n");
+    // A value built only from known variables is still decided
+    let expression = evaluator.compile("config.tags").unwrap();
+    let partial = evaluator.partial_evaluate(&expression, &json!({ "config": { "tags": ["a"] } }));
+    assert_eq!(partial.value(), Some(&json!(["a"])));
+}
@@ -0,0 +1,133 @@
+use exprimo::{AccessPolicy, Capability, ErrorKind, Evaluator};
+use serde_json::{json, Value};
+
+mod common;
+
+fn evaluator(policy: AccessPolicy) -> Evaluator {
+    let mut evaluator = common::evaluator(&[
+        ("user", json!({"name": "Ada", "tier": "gold"})),
+        ("secret", Value::String("hunter2".to_string())),
+    ]);
+    evaluator.register_fn("lookup", |id: String| id.len());
+    evaluator.register_fn("lookdown", |id: String| id.is_empty());
+    evaluator.register_fn("shutdown", || true);
+    evaluator.set_access_policy(policy);
+    evaluator
+}
+
+fn forbidden(evaluator: &Evaluator, expression: &str) -> Option<(Capability, String)> {
+    match evaluator.evaluate(expression).unwrap_err().into_kind() {
+        ErrorKind::Forbidden { capability, name } => Some((capability, name)),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_allow_lists() {
+    let evaluator = evaluator(
+        AccessPolicy::new()
+            .allow_functions(["lookup"])
+            .allow_methods(["toUpperCase"])
+            .allow_globals(["user"]),
+    );
+
+    assert_eq!(
+        evaluator
+            .evaluate("lookup(user.name.toUpperCase())")
+            .unwrap(),
+        json!(3)
+    );
+
+    assert_eq!(
+        forbidden(&evaluator, "shutdown()"),
+        Some((Capability::Function, "shutdown".to_string()))
+    );
+    assert_eq!(
+        forbidden(&evaluator, "user.name.toLowerCase()"),
+        Some((Capability::Method, "toLowerCase".to_string()))
+    );
+    assert_eq!(
+        forbidden(&evaluator, "secret"),
+        Some((Capability::Global, "secret".to_string()))
+    );
+    assert_eq!(
+        forbidden(&evaluator, "secret.length"),
+        Some((Capability::Global, "secret".to_string()))
+    );
+
+    let error = evaluator.evaluate("user.tier + secret").unwrap_err();
+    assert_eq!(error.message(), "Use of global 'secret' is not allowed");
+    assert_eq!((error.span().start, error.span().end), (12, 18));
+}
+
+#[test]
+fn test_deny_lists() {
+    let denying = evaluator(
+        AccessPolicy::new()
+            .deny_functions(["shutdown"])
+            .deny_methods(["regexReplace"])
+            .deny_globals(["secret"]),
+    );
+
+    assert_eq!(denying.evaluate("lookup(user.tier)").unwrap(), json!(4));
+    assert_eq!(
+        forbidden(&denying, "shutdown()"),
+        Some((Capability::Function, "shutdown".to_string()))
+    );
+    assert_eq!(
+        forbidden(&denying, "user.name.regexReplace('a', 'b')"),
+        Some((Capability::Method, "regexReplace".to_string()))
+    );
+
+    // A denied name stays forbidden even when it is also allowed
+    let overlapping = evaluator(
+        AccessPolicy::new()
+            .allow_globals(["user", "secret"])
+            .deny_globals(["secret"]),
+    );
+    assert_eq!(
+        forbidden(&overlapping, "secret"),
+        Some((Capability::Global, "secret".to_string()))
+    );
+}
+
+#[test]
+fn test_suggestions_skip_forbidden_names() {
+    let evaluator = evaluator(AccessPolicy::new().deny_functions(["lookup"]));
+
+    let error = evaluator.evaluate("lokup('x')").unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::UndefinedFunction { .. }));
+    assert_eq!(error.suggestion(), None);
+
+    let error = evaluator.evaluate("lookdwn('x')").unwrap_err();
+    assert_eq!(error.suggestion(), Some("lookdown"));
+}
+
+#[test]
+fn test_validate_policy() {
+    let evaluator = evaluator(
+        AccessPolicy::new()
+            .allow_functions(["lookup"])
+            .deny_methods(["trim"])
+            .deny_globals(["secret"]),
+    );
+
+    let diagnostics = evaluator.validate("shutdown() || secret.trim() == lookup(user.name)");
+    let kinds: Vec<_> = diagnostics
+        .iter()
+        .map(|d| match d.kind() {
+            ErrorKind::Forbidden { capability, name } => (*capability, name.as_str()),
+            kind => panic!("unexpected diagnostic: {}", kind),
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (Capability::Function, "shutdown"),
+            (Capability::Global, "secret"),
+            (Capability::Method, "trim"),
+        ]
+    );
+
+    assert!(evaluator.validate("lookup(user.name)").is_empty());
+}
@@ -0,0 +1,110 @@
+use exprimo::{ErrorKind, Evaluator, Limit, RegexPolicy};
+use serde_json::Value;
+
+mod common;
+
+fn evaluator(policy: RegexPolicy) -> Evaluator {
+    let mut evaluator = common::evaluator(&[
+        ("s", Value::String("hello world".to_string())),
+        ("pattern", Value::String("o".to_string())),
+    ]);
+    evaluator.set_regex_policy(policy);
+    evaluator
+}
+
+#[test]
+fn test_pattern_size_limit() {
+    let evaluator = evaluator(RegexPolicy::default());
+
+    let error = evaluator
+        .evaluate("s.regexReplace('(a{1000}){1000}', '')")
+        .unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        ErrorKind::LimitExceeded {
+            limit: Limit::RegexSize,
+            max: 1048576
+        }
+    ));
+    assert_eq!(error.message(), "regex size limit of 1048576 exceeded");
+
+    let error = evaluator.evaluate("s.regexReplace('(', '')").unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::InvalidArguments { .. }));
+
+    assert_eq!(
+        evaluator.evaluate("s.regexReplace('a{10}', '')").unwrap(),
+        Value::String("hello world".to_string())
+    );
+}
+
+#[test]
+fn test_dynamic_patterns() {
+    let lenient = evaluator(RegexPolicy::default());
+    assert_eq!(
+        lenient.evaluate("s.regexReplace(pattern, '0')").unwrap(),
+        Value::String("hell0 w0rld".to_string())
+    );
+
+    let evaluator = evaluator(RegexPolicy {
+        allow_dynamic: false,
+        ..RegexPolicy::default()
+    });
+    let error = evaluator
+        .evaluate("s.regexReplace(pattern, '0')")
+        .unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::DynamicPattern { .. }));
+    assert_eq!(
+        error.message(),
+        "'regexReplace' requires a string literal pattern"
+    );
+    assert_eq!((error.span().start, error.span().end), (15, 22));
+
+    assert_eq!(
+        evaluator.evaluate("s.regexReplace('o', '0')").unwrap(),
+        Value::String("hell0 w0rld".to_string())
+    );
+}
+
+#[test]
+fn test_pattern_cache() {
+    // A single slot makes every other pattern evict the previous one
+    for cache_capacity in [0, 1, 64] {
+        let evaluator = evaluator(RegexPolicy {
+            cache_capacity,
+            ..RegexPolicy::default()
+        });
+        for _ in 0..3 {
+            assert_eq!(
+                evaluator.evaluate("s.regexReplace('l+', 'L')").unwrap(),
+                Value::String("heLo worLd".to_string())
+            );
+            assert_eq!(
+                evaluator.evaluate("s.regexReplace('[aeiou]', '')").unwrap(),
+                Value::String("hll wrld".to_string())
+            );
+        }
+    }
+}
+
+#[test]
+fn test_validate_patterns() {
+    let evaluator = evaluator(RegexPolicy {
+        allow_dynamic: false,
+        ..RegexPolicy::default()
+    });
+
+    let diagnostics =
+        evaluator.validate("s.regexReplace(pattern, '') + s.regexReplace('(a{1000}){1000}', '')");
+    let kinds: Vec<_> = diagnostics.iter().map(|d| d.kind()).collect();
+    assert_eq!(kinds.len(), 2);
+    assert!(matches!(kinds[0], ErrorKind::DynamicPattern { .. }));
+    assert!(matches!(
+        kinds[1],
+        ErrorKind::LimitExceeded {
+            limit: Limit::RegexSize,
+            ..
+        }
+    ));
+
+    assert!(evaluator.validate("s.regexReplace('o', '0')").is_empty());
+}
@@ -0,0 +1,106 @@
+use exprimo::Scope;
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::sync::Mutex;
+
+mod common;
+
+/// Resolves `user.*` paths from a pretend database, recording every lookup.
+#[derive(Default)]
+struct UserDatabase {
+    lookups: Mutex<Vec<String>>,
+}
+
+impl Scope for UserDatabase {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        self.lookups.lock().unwrap().push(name.to_string());
+        match name {
+            "user" => Some(Cow::Owned(json!({
+                "name": "ada",
+                "profile": { "tier": "gold", "score": 98 }
+            }))),
+            _ => None,
+        }
+    }
+
+    fn get_path(&self, name: &str, path: &[&str]) -> Option<Cow<'_, Value>> {
+        let full_path = std::iter::once(name)
+            .chain(path.iter().copied())
+            .collect::<Vec<_>>()
+            .join(".");
+        self.lookups.lock().unwrap().push(full_path.clone());
+        match full_path.as_str() {
+            "user.profile.tier" => Some(Cow::Owned(Value::from("gold"))),
+            _ if name == "user" => Some(Cow::Owned(Value::Null)),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_resolver_path_callback() {
+    let database = UserDatabase::default();
+
+    let evaluator = common::with_scope(Value::Null);
+
+    let res1 = evaluator
+        .evaluate_with("user.profile.tier == 'gold'", &database)
+        .unwrap();
+    let res2 = evaluator
+        .evaluate_with("user.profile.missing", &database)
+        .unwrap();
+    let res3 = evaluator.evaluate_with("user.name", &database).unwrap();
+
+    assert_eq!(res1, true);
+    assert_eq!(res2, Value::Null);
+    assert_eq!(res3, Value::Null);
+    assert_eq!(
+        *database.lookups.lock().unwrap(),
+        vec!["user.profile.tier", "user.profile.missing", "user.name"]
+    );
+}
+
+#[test]
+fn test_resolver_as_base_scope() {
+    let evaluator = common::with_scope(UserDatabase::default());
+
+    let res1 = evaluator.evaluate("user.profile.tier").unwrap();
+    let res2 = evaluator.evaluate("user.name == null").unwrap();
+    let res3 = evaluator
+        .evaluate_with(
+            "user.profile.tier + '/' + region",
+            &json!({ "region": "eu" }),
+        )
+        .unwrap();
+
+    assert_eq!(res1, "gold");
+    assert_eq!(res2, true);
+    assert_eq!(res3, "gold/eu");
+}
+
+struct Defaults;
+
+impl Scope for Defaults {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        match name {
+            "settings" => Some(Cow::Owned(
+                json!({ "retries": 3, "backoff": { "ms": 250 } }),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_resolver_default_path_walk() {
+    let evaluator = common::with_scope(Defaults);
+
+    let res1 = evaluator
+        .evaluate("settings.backoff.ms * settings.retries")
+        .unwrap();
+    let res2 = evaluator.evaluate("settings.retries.count").unwrap();
+
+    assert_eq!(res1, 750.0);
+    assert_eq!(res2, Value::Null);
+    assert!(evaluator.evaluate("other").is_err());
+}
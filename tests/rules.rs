@@ -0,0 +1,220 @@
+use exprimo::{CancellationToken, ErrorKind, Evaluator, MatchMode, RuleSet, Scope};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::with_scope(Value::Null)
+}
+
+fn rules(evaluator: &Evaluator, rules: &[(&str, &str)]) -> RuleSet {
+    RuleSet::new(
+        rules
+            .iter()
+            .map(|(name, source)| (*name, evaluator.compile(source).unwrap())),
+    )
+}
+
+/// Resolves `event.*` paths, recording every lookup.
+#[derive(Default)]
+struct Event {
+    lookups: Mutex<Vec<String>>,
+}
+
+impl Scope for Event {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        self.get_path(name, &[])
+    }
+
+    fn get_path(&self, name: &str, path: &[&str]) -> Option<Cow<'_, Value>> {
+        let full_path = std::iter::once(name)
+            .chain(path.iter().copied())
+            .collect::<Vec<_>>()
+            .join(".");
+        self.lookups.lock().unwrap().push(full_path.clone());
+        match full_path.as_str() {
+            "event.amount" => Some(Cow::Owned(json!(250))),
+            "event.country" => Some(Cow::Owned(json!("NZ"))),
+            _ if name == "event" => Some(Cow::Owned(Value::Null)),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_evaluate_rules() {
+    let evaluator = evaluator();
+    let rules = rules(
+        &evaluator,
+        &[
+            ("large", "event.amount > 100"),
+            ("local", "event.country == 'NZ'"),
+            ("both", "event.amount > 100 && event.country == 'NZ'"),
+            ("broken", "event.amount / 0"),
+        ],
+    );
+
+    let results = evaluator.evaluate_rules(&rules, &json!({ "event": { "amount": 50 } }));
+    assert_eq!(results.len(), 4);
+    assert_eq!(results["large"].as_ref().unwrap(), &json!(false));
+    assert_eq!(results["local"].as_ref().unwrap(), &json!(false));
+    assert_eq!(results["both"].as_ref().unwrap(), &json!(false));
+    let error = results["broken"].as_ref().unwrap_err();
+    assert_eq!(error.to_string(), "Division by zero (line 1, column 1)");
+    assert_eq!(error.expression(), "event.amount / 0");
+}
+
+#[test]
+fn test_shared_lookups() {
+    let evaluator = evaluator();
+    let rules = rules(
+        &evaluator,
+        &[
+            ("large", "event.amount > 100"),
+            ("local", "event.country == 'NZ'"),
+            ("both", "event.amount > 100 && event.country == 'NZ'"),
+            ("huge", "event.amount > 1000"),
+        ],
+    );
+
+    let event = Event::default();
+    let results = evaluator.evaluate_rules(&rules, &event);
+    assert_eq!(results["both"].as_ref().unwrap(), &json!(true));
+    assert_eq!(results["huge"].as_ref().unwrap(), &json!(false));
+
+    // Each path is resolved once, however many rules read it
+    let mut lookups = event.lookups.into_inner().unwrap();
+    lookups.sort();
+    assert_eq!(lookups, ["event.amount", "event.country"]);
+}
+
+#[test]
+fn test_sharing_survives_moving_the_set() {
+    let evaluator = evaluator();
+    let rules = rules(
+        &evaluator,
+        &[
+            ("large", "event.amount > 100"),
+            ("larger", "event.amount > 100 && event.amount > 200"),
+        ],
+    );
+    let moved = vec![rules];
+    let rules = Box::new(moved.into_iter().next().unwrap());
+
+    let event = Event::default();
+    let results = evaluator.evaluate_rules(&rules, &event);
+    assert_eq!(results["larger"].as_ref().unwrap(), &json!(true));
+    assert_eq!(event.lookups.into_inner().unwrap(), ["event.amount"]);
+}
+
+#[test]
+fn test_functions_are_not_shared() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut evaluator = evaluator();
+    let counter = calls.clone();
+    evaluator.register_fn("next", move || {
+        counter.fetch_add(1, Ordering::SeqCst) as f64
+    });
+
+    let rules = rules(
+        &evaluator,
+        &[("first", "next() + 1"), ("second", "next() + 1")],
+    );
+    let results = evaluator.evaluate_rules(&rules, &json!({}));
+    assert_eq!(results["first"].as_ref().unwrap(), &json!(1.0));
+    assert_eq!(results["second"].as_ref().unwrap(), &json!(2.0));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_failures_point_into_each_rule() {
+    let mut evaluator = evaluator();
+    evaluator.set_strict_properties(true);
+    let rules = rules(
+        &evaluator,
+        &[("a", "user.nmae == 'x'"), ("b", "1 + (user.nmae)")],
+    );
+
+    let results = evaluator.evaluate_rules(&rules, &json!({ "user": { "name": "x" } }));
+    let (a, b) = (
+        results["a"].as_ref().unwrap_err(),
+        results["b"].as_ref().unwrap_err(),
+    );
+    assert_eq!((a.span().start, a.span().end), (0, 9));
+    assert_eq!((b.span().start, b.span().end), (5, 14));
+    assert_eq!(b.suggestion(), Some("user.name"));
+}
+
+#[test]
+fn test_match_rules() {
+    let evaluator = evaluator();
+    let rules = rules(
+        &evaluator,
+        &[
+            ("small", "amount < 10"),
+            ("broken", "amount.trim()"),
+            ("medium", "amount >= 10 && amount < 100"),
+            ("positive", "amount > 0"),
+            ("tagged", "tag"),
+        ],
+    );
+    let variables = json!({ "amount": 42, "tag": "vip" });
+
+    let all = evaluator.match_rules(&rules, &variables, MatchMode::All);
+    assert_eq!(all.matched, ["medium", "positive", "tagged"]);
+    assert_eq!(all.errors.len(), 1);
+    assert_eq!(all.errors[0].0, "broken");
+
+    let first = evaluator.match_rules(&rules, &variables, MatchMode::First);
+    assert_eq!(first.matched, ["medium"]);
+    assert_eq!(first.errors.len(), 1);
+}
+
+#[test]
+fn test_cancellation() {
+    let token = CancellationToken::new();
+    let mut evaluator = evaluator();
+    let stopper = token.clone();
+    evaluator.register_fn("stop", move || {
+        stopper.cancel();
+        true
+    });
+    let rules = rules(
+        &evaluator,
+        &[
+            ("before", "amount > 0"),
+            ("stopping", "stop()"),
+            ("after", "amount > 0"),
+        ],
+    );
+    let variables = json!({ "amount": 42 });
+
+    // The rules left once the token is cancelled fail
+    let results = evaluator.evaluate_rules_cancellable(&rules, &variables, &token);
+    assert_eq!(results["before"].as_ref().unwrap(), &json!(true));
+    let after = results["after"].as_ref().unwrap_err();
+    assert!(matches!(after.kind(), ErrorKind::Cancelled));
+
+    let matches = evaluator.match_rules_cancellable(&rules, &variables, MatchMode::All, &token);
+    assert!(matches.matched.is_empty());
+    assert_eq!(matches.errors.len(), 3);
+    assert!(matches
+        .errors
+        .iter()
+        .all(|(_, error)| matches!(error.kind(), ErrorKind::Cancelled)));
+}
+
+#[test]
+fn test_rule_names() {
+    let evaluator = evaluator();
+    let rules = rules(&evaluator, &[("a", "1"), ("b", "2"), ("a", "3")]);
+
+    // A later rule replaces an earlier one with the same name, keeping its place
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules.names().collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!(rules.get("a").unwrap().source(), "3");
+    assert!(rules.get("c").is_none());
+}
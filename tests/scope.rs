@@ -0,0 +1,73 @@
+use exprimo::{Evaluator, Scope};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    let mut evaluator =
+        common::evaluator(&[("limit", Value::from(100)), ("tenant", Value::from("acme"))]);
+    evaluator.register_fn("double", |x: f64| x * 2.0);
+    evaluator
+}
+
+#[test]
+fn test_evaluate_with_json_payload() {
+    let evaluator = evaluator();
+    let expression = evaluator
+        .compile("event.user.tier == 'gold' && double(event.amount) > limit")
+        .unwrap();
+
+    let events = [
+        json!({ "event": { "user": { "tier": "gold" }, "amount": 60 } }),
+        json!({ "event": { "user": { "tier": "gold" }, "amount": 40 } }),
+        json!({ "event": { "user": { "tier": "silver" }, "amount": 60 } }),
+    ];
+
+    let results: Vec<Value> = events
+        .iter()
+        .map(|event| evaluator.evaluate_expression(&expression, event).unwrap())
+        .collect();
+
+    assert_eq!(results, vec![true, false, false]);
+}
+
+#[test]
+fn test_call_scope_falls_through_to_base() {
+    let evaluator = evaluator();
+
+    let res1 = evaluator
+        .evaluate_with("tenant + ':' + id", &json!({ "id": 7 }))
+        .unwrap();
+    let res2 = evaluator
+        .evaluate_with("tenant", &json!({ "tenant": "globex" }))
+        .unwrap();
+    let res3 = evaluator.evaluate_with("limit", &json!([1, 2])).unwrap();
+
+    assert_eq!(res1, "acme:7");
+    assert_eq!(res2, "globex");
+    assert_eq!(res3, 100.0);
+    assert!(evaluator.evaluate_with("missing", &json!({})).is_err());
+}
+
+struct Env;
+
+impl Scope for Env {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        match name {
+            "region" => Some(Cow::Owned(Value::from("eu-west-1"))),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_custom_scope() {
+    let evaluator = evaluator();
+
+    let res1 = evaluator
+        .evaluate_with("region.startsWith('eu') && tenant == 'acme'", &Env)
+        .unwrap();
+
+    assert_eq!(res1, true);
+}
@@ -0,0 +1,130 @@
+use exprimo::{Evaluator, Expression};
+use serde_json::{json, Value};
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::evaluator(&[])
+}
+
+#[test]
+fn test_json_form() {
+    let expression = evaluator().compile("a > 1").unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&expression).unwrap(),
+        json!({
+            "version": 1,
+            "source": "a > 1",
+            "root": {
+                "kind": { "binary": {
+                    "op": "gt",
+                    "left": { "kind": { "identifier": "a" }, "span": { "start": 0, "end": 1 } },
+                    "right": { "kind": { "literal": 1.0 }, "span": { "start": 4, "end": 5 } }
+                } },
+                "span": { "start": 0, "end": 5 }
+            }
+        })
+    );
+}
+
+#[test]
+fn test_json_round_trip() {
+    let evaluator = evaluator();
+    let variables = json!({ "user": { "name": " Ada ", "age": 36 }, "tags": ["a", "b"] });
+
+    for source in [
+        "user.name.trim().toUpperCase() + '!'",
+        "user?.age >= 18 ? tags.join(',') : null",
+        "!(user.age % 2 === 0) || -user.age < 0",
+    ] {
+        let expression = evaluator.compile(source).unwrap();
+        let json = serde_json::to_string(&expression).unwrap();
+        let decoded: Expression = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, expression);
+        assert_eq!(
+            evaluator.evaluate_expression(&decoded, &variables).unwrap(),
+            evaluator
+                .evaluate_expression(&expression, &variables)
+                .unwrap()
+        );
+    }
+
+    // Errors still point into the original source
+    let decoded: Expression = serde_json::from_value(
+        serde_json::to_value(evaluator.compile("1 / (a - a)").unwrap()).unwrap(),
+    )
+    .unwrap();
+    let error = evaluator
+        .evaluate_expression(&decoded, &json!({ "a": 1 }))
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Division by zero (line 1, column 1)");
+}
+
+#[test]
+fn test_version_mismatch() {
+    let expression = evaluator().compile("a").unwrap();
+    let mut json = serde_json::to_value(&expression).unwrap();
+    json["version"] = json!(2);
+
+    let error = serde_json::from_value::<Expression>(json).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Unsupported compiled expression format version 2, expected 1"));
+
+    let error =
+        serde_json::from_value::<Expression>(json!({ "source": "a", "root": null })).unwrap_err();
+    assert!(error.to_string().contains("invalid type: null"));
+}
+
+#[test]
+fn test_invalid_spans() {
+    let mut json = serde_json::to_value(evaluator().compile("a + b").unwrap()).unwrap();
+    json["source"] = Value::from("a");
+
+    let error = serde_json::from_value::<Expression>(json).unwrap_err();
+    assert_eq!(error.to_string(), "node span outside the expression source");
+}
+
+#[test]
+fn test_composite_literals() {
+    // The parser has no array or object literals, so a tree holding one can't be printed
+    let mut json = serde_json::to_value(evaluator().compile("a > 1").unwrap()).unwrap();
+    json["root"]["kind"]["binary"]["right"]["kind"]["literal"] = json!([1]);
+
+    let error = serde_json::from_value::<Expression>(json).unwrap_err();
+    assert_eq!(error.to_string(), "array or object literal in the tree");
+}
+
+#[cfg(feature = "binary")]
+#[test]
+fn test_binary_round_trip() {
+    use exprimo::DecodeError;
+
+    let evaluator = evaluator();
+    let expression = evaluator
+        .compile("event.amount > 100 && event.currency == 'EUR'")
+        .unwrap();
+
+    let bytes = expression.to_bytes();
+    assert!(bytes.len() < serde_json::to_vec(&expression).unwrap().len());
+    assert_eq!(Expression::from_bytes(&bytes).unwrap(), expression);
+
+    let mut newer = serde_json::to_value(&expression).unwrap();
+    newer["version"] = json!(2);
+    let mut bytes = Vec::new();
+    ciborium::into_writer(&newer, &mut bytes).unwrap();
+    assert!(matches!(
+        Expression::from_bytes(&bytes),
+        Err(DecodeError::Version {
+            found: 2,
+            expected: 1
+        })
+    ));
+
+    assert!(matches!(
+        Expression::from_bytes(b"not cbor"),
+        Err(DecodeError::Invalid { .. })
+    ));
+}
@@ -0,0 +1,84 @@
+use exprimo::{ErrorKind, Evaluator};
+use serde_json::{json, Value};
+
+mod common;
+
+fn evaluator(strict: bool) -> Evaluator {
+    let mut evaluator = common::evaluator(&[(
+        "event",
+        json!({ "user": { "id": 7, "manager": null }, "tags": ["a"], "count": 3 }),
+    )]);
+    evaluator.set_strict_properties(strict);
+    evaluator.register_fn("identity", |value: Value| value);
+    evaluator
+}
+
+#[test]
+fn test_lenient_by_default() {
+    let evaluator = evaluator(false);
+
+    assert_eq!(evaluator.evaluate("event.usr.id").unwrap(), Value::Null);
+    assert_eq!(evaluator.evaluate("evnt.user").unwrap(), Value::Null);
+    assert_eq!(evaluator.evaluate("event.user?.id").unwrap(), 7);
+}
+
+#[test]
+fn test_strict_missing_property() {
+    let evaluator = evaluator(true);
+
+    let err1 = evaluator.evaluate("event.user.usr == 7").unwrap_err();
+    let err2 = evaluator.evaluate("event.count.value").unwrap_err();
+    let err3 = evaluator.evaluate("evnt.user").unwrap_err();
+    let err4 = evaluator.evaluate("identity(event).usr.id").unwrap_err();
+
+    assert!(matches!(
+        err1.kind(),
+        ErrorKind::MissingProperty { path, property } if path == "event.user" && property == "usr"
+    ));
+    assert_eq!(err1.message(), "`event.user` has no property `usr`");
+    assert_eq!(
+        &err1.expression()[err1.span().start..err1.span().end],
+        "event.user.usr"
+    );
+    assert_eq!(err1.suggestion(), None);
+    assert_eq!(
+        evaluator.evaluate("event.usr.id").unwrap_err().suggestion(),
+        Some("event.user")
+    );
+    assert_eq!(err2.message(), "`event.count` has no property `value`");
+    assert!(matches!(err3.kind(), ErrorKind::UndefinedVariable { name } if name == "evnt"));
+    assert_eq!(err3.suggestion(), Some("event"));
+    assert_eq!(err4.message(), "`identity(event)` has no property `usr`");
+}
+
+#[test]
+fn test_strict_allows_null_values_and_optional_reads() {
+    let evaluator = evaluator(true);
+
+    assert_eq!(
+        evaluator.evaluate("event.user.manager").unwrap(),
+        Value::Null
+    );
+    assert_eq!(
+        evaluator.evaluate("event.user.manager?.name").unwrap(),
+        Value::Null
+    );
+    assert_eq!(
+        evaluator.evaluate("event.user?.usr.id").unwrap(),
+        Value::Null
+    );
+    assert_eq!(evaluator.evaluate("evnt?.user").unwrap(), Value::Null);
+    assert_eq!(evaluator.evaluate("event.user.id").unwrap(), 7);
+    assert_eq!(
+        evaluator
+            .evaluate_with(
+                "event.user.id",
+                &json!({ "event": { "user": { "id": 1 } } })
+            )
+            .unwrap(),
+        1
+    );
+
+    let err = evaluator.evaluate("event.user.manager.name").unwrap_err();
+    assert_eq!(err.message(), "`event.user.manager` has no property `name`");
+}
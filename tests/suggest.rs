@@ -0,0 +1,72 @@
+use exprimo::{ErrorKind, Evaluator};
+use serde_json::{json, Value};
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    let mut evaluator = common::evaluator(&[
+        ("threshold", Value::from(10)),
+        (
+            "user",
+            json!({ "name": "ada", "profile": { "tier": "gold" } }),
+        ),
+    ]);
+    evaluator.register_fn("double", |x: f64| x * 2.0);
+    evaluator
+}
+
+#[test]
+fn test_suggest_identifiers_and_functions() {
+    let evaluator = evaluator();
+
+    let err1 = evaluator.evaluate("treshold > 1").unwrap_err();
+    let err2 = evaluator
+        .evaluate_with("amout > 1", &json!({ "amount": 5 }))
+        .unwrap_err();
+    let err3 = evaluator.evaluate("dubble(2)").unwrap_err();
+    let err4 = evaluator.evaluate("completely_unrelated").unwrap_err();
+
+    assert_eq!(err1.suggestion(), Some("threshold"));
+    assert_eq!(err2.suggestion(), Some("amount"));
+    assert_eq!(err3.suggestion(), Some("double"));
+    assert_eq!(err4.suggestion(), None);
+    assert_eq!(
+        err1.to_string(),
+        "Identifier 'treshold' not found in context. (line 1, column 1); did you mean `threshold`?"
+    );
+}
+
+#[test]
+fn test_suggest_methods() {
+    let evaluator = evaluator();
+
+    let err1 = evaluator.evaluate("user.name.toUppercase()").unwrap_err();
+    let err2 = evaluator
+        .evaluate("user.name.split(',').jion('-')")
+        .unwrap_err();
+
+    assert!(matches!(err1.kind(), ErrorKind::UnknownMethod { .. }));
+    assert_eq!(err1.suggestion(), Some("toUpperCase"));
+    assert_eq!(err2.suggestion(), Some("join"));
+    assert_eq!(
+        format!("{:#}", err1),
+        "error: Unknown string method: toUppercase\n --> 1:1\n  |\n1 | user.name.toUppercase()\n  | ^^^^^^^^^^^^^^^^^^^^^^^\n  = help: did you mean `toUpperCase`?"
+    );
+
+    let diagnostics = evaluator.validate("'abc'.startWith('a') && dubble(1)");
+    let suggestions: Vec<_> = diagnostics.iter().map(|d| d.suggestion()).collect();
+    assert_eq!(suggestions, vec![Some("startsWith"), Some("double")]);
+}
+
+#[test]
+fn test_suggest_properties() {
+    let evaluator = evaluator();
+
+    let err1 = evaluator.evaluate("user.nmae.toUpperCase()").unwrap_err();
+    let err2 = evaluator
+        .evaluate("user.profile.teir.startsWith('g')")
+        .unwrap_err();
+
+    assert_eq!(err1.suggestion(), Some("user.name"));
+    assert_eq!(err2.suggestion(), Some("user.profile.tier"));
+}
@@ -0,0 +1,72 @@
+use exprimo::{ErrorKind, EvaluationError, Evaluator};
+use serde_json::Value;
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::evaluator(&[("a", Value::from(1)), ("b", Value::from(2))])
+}
+
+fn parse_err(evaluator: &Evaluator, expression: &str) -> EvaluationError {
+    let err = evaluator.compile(expression).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Parse { .. }), "{:?}", err);
+    err
+}
+
+#[test]
+fn test_syntax_errors() {
+    let evaluator = evaluator();
+
+    let err1 = parse_err(&evaluator, "a +");
+    let err2 = parse_err(&evaluator, "(a");
+    let err3 = parse_err(&evaluator, "a ? b");
+    let err4 = parse_err(&evaluator, "1 +* 2");
+
+    assert_eq!(err1.message(), "Expected an expression, but found none");
+    assert_eq!((err1.span().start, err1.span().end), (3, 3));
+    assert_eq!(err2.column(), 3);
+    assert_eq!(err3.span().start, 5);
+    assert_eq!((err4.span().start, err4.span().end), (3, 4));
+    assert!(evaluator.evaluate("a +").is_err());
+}
+
+#[test]
+fn test_trailing_input_rejected() {
+    let evaluator = evaluator();
+
+    let err1 = parse_err(&evaluator, "a b");
+    let err2 = parse_err(&evaluator, "a)");
+    let err3 = parse_err(&evaluator, "a; b");
+    let err4 = parse_err(&evaluator, "a\nb");
+
+    assert_eq!((err1.span().start, err1.span().end), (2, 3));
+    assert_eq!(err2.span().start, 1);
+    assert_eq!(
+        err3.message(),
+        "Expected a single expression, but found another statement"
+    );
+    assert_eq!(err4.line(), 2);
+    assert_eq!(evaluator.evaluate("a + b;").unwrap(), 3.0);
+}
+
+#[test]
+fn test_strict_syntax() {
+    let mut evaluator = evaluator();
+
+    let lenient = evaluator.compile("a > 0 ? 1 : [1, 2]").unwrap();
+    assert!(evaluator
+        .evaluate_expression(&lenient, &Value::Null)
+        .is_ok());
+
+    evaluator.set_strict_syntax(true);
+
+    let err1 = evaluator.compile("a > 0 ? 1 : [1, 2]").unwrap_err();
+    let err2 = evaluator.compile("a in b").unwrap_err();
+
+    assert!(matches!(err1.kind(), ErrorKind::Unsupported { .. }));
+    assert_eq!((err1.span().start, err1.span().end), (12, 18));
+    assert!(
+        matches!(err2.kind(), ErrorKind::Unsupported { construct } if construct == "binary operator 'in'")
+    );
+    assert!(evaluator.compile("a > 0 || b.length() > 1").is_ok());
+}
@@ -0,0 +1,228 @@
+use exprimo::{ErrorKind, Evaluator, Scope, Type, TypeContext};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    let mut evaluator = common::evaluator(&[("threshold", json!(10))]);
+    evaluator.register_fn("lookup", |id: String| id.len());
+    evaluator
+}
+
+fn schema() -> TypeContext {
+    TypeContext::from_schema(&json!({
+        "type": "object",
+        "properties": {
+            "event": {
+                "type": "object",
+                "properties": {
+                    "amount": { "type": "number" },
+                    "currency": { "type": ["string", "null"] },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "user": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "string" },
+                            "verified": { "type": "boolean" }
+                        },
+                        "additionalProperties": false
+                    }
+                }
+            }
+        }
+    }))
+    .variable("threshold", Type::Number)
+    .function("lookup", [Type::String], Type::Number)
+}
+
+fn messages(evaluator: &Evaluator, expression: &str) -> Vec<String> {
+    evaluator
+        .check_types(expression, &schema())
+        .iter()
+        .map(|diagnostic| diagnostic.message())
+        .collect()
+}
+
+#[test]
+fn test_infer_type() {
+    let evaluator = evaluator();
+    let infer = |expression| evaluator.infer_type(expression, &schema()).unwrap();
+
+    assert_eq!(infer("event.amount * 2"), Type::Number);
+    assert_eq!(infer("event.currency.toUpperCase()"), Type::String);
+    assert_eq!(infer("event.tags"), Type::array(Type::String));
+    assert_eq!(infer("event.user.verified && threshold > 3"), Type::Boolean);
+    assert_eq!(infer("lookup(event.user.id) + threshold"), Type::Number);
+    assert_eq!(infer("event.amount > 0 ? 'large' : 'small'"), Type::String);
+    assert_eq!(infer("event.amount > 0 ? 'large' : 0"), Type::Any);
+    // Objects not closed by the schema may hold anything else
+    assert_eq!(infer("event.source"), Type::Any);
+}
+
+#[test]
+fn test_method_on_wrong_type() {
+    let evaluator = evaluator();
+
+    let diagnostics = evaluator.check_types("event.amount.toUpperCase()", &schema());
+    assert_eq!(diagnostics.len(), 1);
+    assert!(matches!(
+        diagnostics[0].kind(),
+        ErrorKind::UnknownMethod {
+            receiver_type: "number",
+            ..
+        }
+    ));
+    assert_eq!(
+        (diagnostics[0].span().start, diagnostics[0].span().end),
+        (0, 26)
+    );
+
+    assert_eq!(
+        messages(
+            &evaluator,
+            "event.tags.join(', ').trim() + event.tags.trim()"
+        ),
+        ["Unknown array method: trim"]
+    );
+}
+
+#[test]
+fn test_type_errors() {
+    let evaluator = evaluator();
+
+    assert_eq!(
+        messages(&evaluator, "event.tags * 2 > -event.user"),
+        [
+            "Cannot apply '*' to array and number",
+            "Cannot apply '-' to object"
+        ]
+    );
+    assert_eq!(
+        messages(&evaluator, "lookup(event.amount)"),
+        ["'lookup' expects string as argument 1, got number"]
+    );
+    assert_eq!(
+        messages(&evaluator, "event.user.name == evnt.amount"),
+        [
+            "`event.user` has no property `name`",
+            "Identifier 'evnt' not found in context."
+        ]
+    );
+
+    let diagnostics = evaluator.check_types("event.amount > treshold", &schema());
+    assert_eq!(diagnostics[0].suggestion(), Some("threshold"));
+
+    // Missing values are fine wherever a value is expected
+    assert!(evaluator
+        .check_types(
+            "lookup(event.currency) > 0 && missing?.value == null",
+            &schema()
+        )
+        .is_empty());
+}
+
+#[test]
+fn test_argument_count() {
+    let mut evaluator = evaluator();
+    // Registered without a known arity, so only its declared type says how many it takes
+    evaluator.register_function("score", |args: Vec<Value>| {
+        Ok::<_, String>(Value::from(args.len()))
+    });
+    let context = schema().function("score", [Type::Number, Type::Number], Type::Number);
+
+    let diagnostics = evaluator.check_types("1 + score(event.amount)", &context);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message(),
+        "'score' expected 2 argument(s), got 1"
+    );
+    assert_eq!(
+        (diagnostics[0].span().start, diagnostics[0].span().end),
+        (4, 23)
+    );
+    assert_eq!(
+        messages(&evaluator, "lookup('a', 'b') > 0"),
+        ["'lookup' expected 1 argument(s), got 2"]
+    );
+    assert!(evaluator
+        .check_types("score(1, event.amount) > 0", &context)
+        .is_empty());
+}
+
+#[test]
+fn test_strict_properties() {
+    let mut evaluator = evaluator();
+    assert!(evaluator
+        .check_types("event.amout > 0", &schema())
+        .is_empty());
+
+    evaluator.set_strict_properties(true);
+    let diagnostics = evaluator.check_types("event.amout > 0 || event?.other", &schema());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message(), "`event` has no property `amout`");
+    assert_eq!(diagnostics[0].suggestion(), Some("amount"));
+}
+
+#[test]
+fn test_type_context() {
+    let evaluator = evaluator();
+    let context = TypeContext::new()
+        .variable("user", Type::object([("name", Type::String)]))
+        .variable("scores", Type::array(Type::Number));
+
+    assert_eq!(
+        evaluator
+            .infer_type("user.name.length()", &context)
+            .unwrap(),
+        Type::Number
+    );
+    let diagnostics = evaluator
+        .infer_type("scores.join('-') - user.name.split(',')", &context)
+        .unwrap_err();
+    assert_eq!(
+        diagnostics[0].message(),
+        "Cannot apply '-' to string and array"
+    );
+
+    assert_eq!(
+        Type::of(&json!({"a": [1, null, 2]})),
+        Type::object([("a", Type::array(Type::Number))])
+    );
+    assert_eq!(Type::from_schema(&Value::Bool(true)), Type::Any);
+    assert_eq!(Type::array(Type::Number).to_string(), "array<number>");
+}
+
+/// A scope whose values may only be read when evaluating.
+struct Live;
+
+impl Scope for Live {
+    fn get(&self, name: &str) -> Option<Cow<'_, Value>> {
+        panic!("read {} while checking types", name)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        panic!("listed variables while checking types")
+    }
+}
+
+#[test]
+fn test_only_declared_types_are_read() {
+    let live = common::with_scope(Live);
+    assert_eq!(
+        live.infer_type("threshold + 1", &schema()).unwrap(),
+        Type::Number
+    );
+    let diagnostics = live.check_types("limit > event.amount", &schema());
+    assert_eq!(
+        diagnostics[0].message(),
+        "Identifier 'limit' not found in context."
+    );
+
+    // The evaluator's own context isn't read either
+    let diagnostics = evaluator().check_types("threshold > 1", &TypeContext::new());
+    assert_eq!(
+        diagnostics[0].message(),
+        "Identifier 'threshold' not found in context."
+    );
+}
@@ -0,0 +1,89 @@
+use exprimo::{ErrorKind, Evaluator};
+use serde_json::Value;
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    let mut evaluator = common::evaluator(&[]);
+    evaluator.register_fn("clamp", |x: f64, lo: f64, hi: f64| x.max(lo).min(hi));
+    evaluator.register_function("sum", |args: Vec<Value>| {
+        Ok::<_, String>(Value::from(args.len()))
+    });
+    evaluator
+}
+
+#[test]
+fn test_validate_collects_all_problems() {
+    let evaluator = evaluator();
+
+    let diagnostics = evaluator.validate(
+        "lookup(user.id) && 'abc'.reverse() && clamp(x, 1) && [1, 2] && name.toUpperCase(1) && sum(1, 2, 3)",
+    );
+    let kinds: Vec<&ErrorKind> = diagnostics.iter().map(|d| d.kind()).collect();
+
+    assert_eq!(diagnostics.len(), 4);
+    assert!(matches!(kinds[0], ErrorKind::UndefinedFunction { name } if name == "lookup"));
+    assert!(matches!(
+        kinds[1],
+        ErrorKind::UnknownMethod { receiver_type: "string", method } if method == "reverse"
+    ));
+    assert!(matches!(
+        kinds[2],
+        ErrorKind::ArityMismatch { callee, min: 3, max: 3, found: 2 } if callee == "clamp"
+    ));
+    assert!(matches!(kinds[3], ErrorKind::Unsupported { .. }));
+    assert_eq!(
+        &diagnostics[3].expression()[diagnostics[3].span().start..diagnostics[3].span().end],
+        "[1, 2]"
+    );
+}
+
+#[test]
+fn test_validate_static_receivers() {
+    let evaluator = evaluator();
+
+    let diagnostics1 = evaluator.validate("('a' + b).trim(1)");
+    let diagnostics2 = evaluator.validate("'a,b'.split(',').join('-').toUpperCase()");
+    let diagnostics3 = evaluator.validate("'a'.length().trim()");
+    let diagnostics4 = evaluator.validate("'abc'.substring(1, 2, 3)");
+
+    assert_eq!(diagnostics1.len(), 1);
+    assert_eq!(
+        diagnostics1[0].message(),
+        "'trim' expected 0 argument(s), got 1"
+    );
+    assert!(diagnostics2.is_empty());
+    assert!(matches!(
+        diagnostics3[0].kind(),
+        ErrorKind::UnknownMethod {
+            receiver_type: "number",
+            ..
+        }
+    ));
+    assert_eq!(
+        diagnostics4[0].message(),
+        "'substring' expected 0 to 2 argument(s), got 3"
+    );
+}
+
+#[test]
+fn test_validate_syntax_errors() {
+    let evaluator = evaluator();
+
+    let diagnostics1 = evaluator.validate("a + (b * ) + nope()");
+    let diagnostics2 = evaluator.validate("a b");
+    let diagnostics3 = evaluator.validate("");
+
+    assert!(matches!(diagnostics1[0].kind(), ErrorKind::Parse { .. }));
+    assert!(diagnostics1
+        .iter()
+        .any(|d| matches!(d.kind(), ErrorKind::UndefinedFunction { name } if name == "nope")));
+    assert!(diagnostics1
+        .iter()
+        .all(|d| !matches!(d.kind(), ErrorKind::Unsupported { .. })));
+    assert_eq!(diagnostics2.len(), 1);
+    assert_eq!(diagnostics3.len(), 1);
+    assert!(evaluator
+        .validate("user.age > 18 ? clamp(x, 0, 1) : 0")
+        .is_empty());
+}
@@ -0,0 +1,222 @@
+use exprimo::{
+    AccessPolicy, CancellationToken, ErrorKind, EvaluationError, EvaluationLimits, Evaluator,
+    Program, RegexPolicy,
+};
+use serde_json::{json, Value};
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    let mut evaluator = common::evaluator(&[
+        ("env", json!("prod")),
+        ("config", json!({ "retries": 3, "tags": ["a", "b"] })),
+    ]);
+    evaluator
+        .register_fn("double", |x: f64| x * 2.0)
+        .register_fn("user", || json!({ "name": "Ada", "age": 36 }))
+        .try_register_fn("fail", |reason: String| Err::<f64, _>(reason));
+    evaluator
+}
+
+fn variables() -> Value {
+    json!({
+        "a": 4,
+        "b": 0,
+        "s": " Hello, World ",
+        "flag": true,
+        "pattern": "l+",
+        "user": { "name": "Grace", "address": { "city": "Paris" }, "tags": ["x", "y"] },
+        "items": [1, 2, 3],
+        "missing": null,
+    })
+}
+
+const CORPUS: &[&str] = &[
+    "1",
+    "'text'",
+    "a",
+    "a + 1",
+    "a - 2 * 3 / 4 % 5",
+    "-a + +'3' - !b",
+    "'a' + 1 + 2",
+    "s + a",
+    "a > 3 && b <= 0 || flag",
+    "a == 4 && a != '4' && null == missing",
+    "a === 4 ? 'four' : 'other'",
+    "b ? 1 / b : 'zero'",
+    "flag ? (a > 1 ? 'big' : 'small') : missing",
+    "user.name",
+    "user.address.city.length()",
+    "user.address?.zip",
+    "user.nmae",
+    "user.name.toUpperCase()",
+    "user.tags.join('-')",
+    "config.retries * 2",
+    "config.tags.join(', ')",
+    "env == 'prod'",
+    "s.trim().toLowerCase().split(', ').join('|')",
+    "s.replace('l', 'L').indexOf('L')",
+    "s.regexReplace('l+', '_')",
+    "s.regexReplace(pattern, '_')",
+    "s.substring(1, 6).startsWith('Hell')",
+    "double(a) + double(double(1))",
+    "user().name",
+    "user().address.city",
+    "double(a, b)",
+    "fail('no')",
+    "undefinedFunction(1)",
+    "nope + 1",
+    "usr.name",
+    "1 / b",
+    "a / 0 + nope",
+    "items > 1",
+    "items.join(',').contains('2')",
+    "s.nosuch()",
+    "missing.trim()",
+    "user.nmae.trim()",
+    "a.length()",
+    "typeof a",
+    "a in user",
+    "flag ? typeof a : 1",
+    "!flag ? typeof a : 1",
+    "1 + 2 * (3 - a) > 0 ? s.trim() : user.name",
+];
+
+/// Evaluates each expression both ways and checks the results match, down to the error,
+/// its position and its suggestion.
+fn assert_same(evaluator: &Evaluator, variables: &Value) {
+    for source in CORPUS {
+        // Compiling checks the depth limit before either evaluates
+        let Ok(expression) = evaluator.compile(source) else {
+            continue;
+        };
+        let program = Program::compile(&expression);
+
+        let walked = evaluator.evaluate_expression(&expression, variables);
+        let run = evaluator.evaluate_program(&program, variables);
+        assert_eq!(summary(&run), summary(&walked), "{}", source);
+    }
+}
+
+fn summary(result: &Result<Value, EvaluationError>) -> Result<Value, String> {
+    result.as_ref().cloned().map_err(|e| {
+        format!(
+            "{} at {:?}, suggestion {:?}",
+            e.message(),
+            e.span(),
+            e.suggestion()
+        )
+    })
+}
+
+#[test]
+fn test_same_results_as_tree_walker() {
+    let evaluator = evaluator();
+
+    assert_same(&evaluator, &variables());
+    assert_same(&evaluator, &json!({}));
+}
+
+#[test]
+fn test_same_results_with_strict_properties() {
+    let mut evaluator = evaluator();
+    evaluator.set_strict_properties(true);
+
+    assert_same(&evaluator, &variables());
+    assert_same(&evaluator, &json!({ "user": {} }));
+}
+
+#[test]
+fn test_same_results_with_policies() {
+    let mut evaluator = evaluator();
+    evaluator
+        .set_access_policy(
+            AccessPolicy::new()
+                .deny_functions(["fail"])
+                .deny_methods(["toUpperCase"])
+                .deny_globals(["env"]),
+        )
+        .set_regex_policy(RegexPolicy {
+            allow_dynamic: false,
+            ..RegexPolicy::default()
+        });
+
+    assert_same(&evaluator, &variables());
+}
+
+#[test]
+fn test_limits_trip_at_the_same_node() {
+    // Every budget small enough to stop somewhere inside the larger expressions
+    for max in 1..12 {
+        let mut evaluator = evaluator();
+        evaluator.set_limits(EvaluationLimits {
+            max_steps: max,
+            ..EvaluationLimits::default()
+        });
+        assert_same(&evaluator, &variables());
+
+        evaluator.set_limits(EvaluationLimits {
+            max_depth: max,
+            ..EvaluationLimits::default()
+        });
+        assert_same(&evaluator, &variables());
+
+        evaluator.set_limits(EvaluationLimits {
+            max_string_length: max,
+            ..EvaluationLimits::default()
+        });
+        assert_same(&evaluator, &variables());
+    }
+}
+
+#[test]
+fn test_program_is_reusable() {
+    let evaluator = evaluator();
+    let expression = evaluator
+        .compile("user.address.city == 'Paris' ? a * 2 : a")
+        .unwrap();
+    let program = Program::compile(&expression);
+
+    assert_eq!(program.source(), expression.source());
+    for a in 0..5 {
+        let city = if a % 2 == 0 { "Paris" } else { "Rome" };
+        let variables = json!({ "a": a, "user": { "address": { "city": city } } });
+        let expected = if a % 2 == 0 {
+            json!(a as f64 * 2.0)
+        } else {
+            json!(a)
+        };
+        assert_eq!(
+            evaluator.evaluate_program(&program, &variables).unwrap(),
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_cancellation() {
+    let evaluator = evaluator();
+    let expression = evaluator.compile("a * 2 > 1 && flag").unwrap();
+    let program = Program::compile(&expression);
+
+    let token = CancellationToken::new();
+    assert_eq!(
+        evaluator
+            .evaluate_program_cancellable(&program, &variables(), &token)
+            .unwrap(),
+        json!(true)
+    );
+
+    token.cancel();
+    let error = evaluator
+        .evaluate_program_cancellable(&program, &variables(), &token)
+        .unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::Cancelled));
+
+    // An expired deadline reports a timeout instead
+    let token = CancellationToken::new().with_timeout(std::time::Duration::ZERO);
+    let error = evaluator
+        .evaluate_program_cancellable(&program, &variables(), &token)
+        .unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::Timeout));
+}
@@ -0,0 +1,70 @@
+use exprimo::{Evaluator, Scope};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+
+mod common;
+
+fn evaluator() -> Evaluator {
+    common::evaluator(&[("config", json!({ "limits": { "max": 10 } }))])
+}
+
+#[test]
+fn test_lookups_borrow_from_payload() {
+    let evaluator = evaluator();
+    let payload = json!({
+        "event": {
+            "a": { "b": { "c": (0..1000).collect::<Vec<_>>() } },
+            "kind": "click"
+        }
+    });
+
+    let expression = evaluator.compile("event.a.b.c").unwrap();
+    let result = evaluator.evaluate_borrowed(&expression, &payload).unwrap();
+
+    match result {
+        Cow::Borrowed(value) => assert!(std::ptr::eq(value, &payload["event"]["a"]["b"]["c"])),
+        Cow::Owned(_) => panic!("expected the payload to be borrowed"),
+    }
+
+    let expression = evaluator
+        .compile("event.kind == 'click' ? event.a : config.limits")
+        .unwrap();
+    let result = evaluator.evaluate_borrowed(&expression, &payload).unwrap();
+
+    assert!(matches!(result, Cow::Borrowed(value) if std::ptr::eq(value, &payload["event"]["a"])));
+}
+
+#[test]
+fn test_computed_values_are_owned() {
+    let evaluator = evaluator();
+    let payload = json!({ "event": { "count": 4 } });
+
+    let expression = evaluator
+        .compile("event.count + config.limits.max")
+        .unwrap();
+    let result = evaluator.evaluate_borrowed(&expression, &payload).unwrap();
+    assert!(matches!(result, Cow::Owned(_)));
+    assert_eq!(result.into_owned(), 14.0);
+
+    let expression = evaluator.compile("config.limits.max").unwrap();
+    let result = evaluator.evaluate_borrowed(&expression, &payload).unwrap();
+    assert!(matches!(result, Cow::Borrowed(_)));
+    assert_eq!(*result, 10);
+
+    let expression = evaluator.compile("event.missing.path").unwrap();
+    let result = evaluator.evaluate_borrowed(&expression, &payload).unwrap();
+    assert_eq!(*result, Value::Null);
+}
+
+#[test]
+fn test_borrowing_from_a_scope_object() {
+    let evaluator = evaluator();
+    let payload = json!({ "event": { "kind": "click" } });
+    let scope: &dyn Scope = &payload;
+
+    let expression = evaluator.compile("event.kind").unwrap();
+    let result = evaluator.evaluate_borrowed(&expression, scope).unwrap();
+    assert!(
+        matches!(result, Cow::Borrowed(value) if std::ptr::eq(value, &payload["event"]["kind"]))
+    );
+}